@@ -1,39 +1,47 @@
 // Day 4: Passport Processing
 // https://adventofcode.com/2020/day/4
 
+use adventofcode::records::read_records;
 use lazy_static::lazy_static;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::env;
 use std::fs::File;
 use std::io;
-use std::io::BufRead;
-use std::path::Path;
 
-#[derive(Debug)]
+/// the passport field names, in the order they are written out in batch format
+const FIELDS: [&str; 8] = ["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid", "cid"];
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Passport {
+    #[serde(skip_serializing_if = "Option::is_none")]
     byr: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     iyr: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     eyr: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     hgt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     hcl: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     ecl: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     cid: Option<String>,
 }
 
-impl<'a> Passport {
-    fn new() -> Self {
-        Passport {
-            byr: None,
-            iyr: None,
-            eyr: None,
-            hgt: None,
-            hcl: None,
-            ecl: None,
-            pid: None,
-            cid: None,
-        }
-    }
+/// a parsed Passport together with its validation status, used when exporting passports
+#[derive(Debug, Serialize)]
+struct ValidatedPassport<'a> {
+    valid: bool,
+    #[serde(flatten)]
+    passport: &'a Passport,
+}
 
+impl<'a> Passport {
     fn byr(&mut self, byr: Option<String>) -> &mut Passport {
         self.byr = byr;
         self
@@ -138,6 +146,31 @@ impl<'a> Passport {
         self
     }
 
+    /// returns the value of the field with the given (three letter) `name`
+    fn field(&self, name: &str) -> Option<&str> {
+        let value = match name {
+            "byr" => &self.byr,
+            "iyr" => &self.iyr,
+            "eyr" => &self.eyr,
+            "hgt" => &self.hgt,
+            "hcl" => &self.hcl,
+            "ecl" => &self.ecl,
+            "pid" => &self.pid,
+            "cid" => &self.cid,
+            _ => return None,
+        };
+        value.as_deref()
+    }
+
+    /// returns this passport in the `key:value` batch file format, all fields on a single line
+    fn to_batch_line(&self) -> String {
+        FIELDS
+            .iter()
+            .filter_map(|&name| self.field(name).map(|value| format!("{}:{}", name, value)))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
     /// passport is valid if all required fields are present, AND valid
     /// cid field is ignored in this scenario
     fn is_valid(&self) -> bool {
@@ -151,15 +184,6 @@ impl<'a> Passport {
     }
 }
 
-/// returns an iterator over the lines of the file pointed to by filename
-fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
-where
-    P: AsRef<Path>,
-{
-    let file = File::open(filename)?;
-    Ok(io::BufReader::new(file).lines())
-}
-
 fn parse_byr_line(line: &String, pp: &mut Passport) {
     lazy_static! {
         static ref RE: Regex =
@@ -238,35 +262,167 @@ fn parse_cid_line(line: &String, pp: &mut Passport) {
     }
 }
 
-fn main() {
+/// parse a passport record, whose fields may be spread across multiple lines
+fn parse_passport(record: &[String]) -> Passport {
+    let mut passport = Passport::default();
+    for line in record {
+        parse_byr_line(line, &mut passport);
+        parse_iyr_line(line, &mut passport);
+        parse_eyr_line(line, &mut passport);
+        parse_hgt_line(line, &mut passport);
+        parse_hcl_line(line, &mut passport);
+        parse_ecl_line(line, &mut passport);
+        parse_pid_line(line, &mut passport);
+        parse_cid_line(line, &mut passport);
+    }
+    passport
+}
+
+/// writes `passports` as a JSON array, each passport includes a `valid` flag
+fn write_json<W: io::Write>(passports: &[Passport], writer: W) -> serde_json::Result<()> {
+    let validated: Vec<ValidatedPassport> = passports
+        .iter()
+        .map(|passport| ValidatedPassport {
+            valid: passport.is_valid(),
+            passport,
+        })
+        .collect();
+    serde_json::to_writer_pretty(writer, &validated)
+}
+
+/// writes `passports` as CSV with a header row. Missing fields are written as empty columns
+fn write_csv<W: io::Write>(passports: &[Passport], writer: W) -> csv::Result<()> {
+    let mut wtr = csv::Writer::from_writer(writer);
+    wtr.write_record(std::iter::once("valid").chain(FIELDS.iter().copied()))?;
+    for passport in passports {
+        let valid = passport.is_valid().to_string();
+        let fields = FIELDS
+            .iter()
+            .map(|&name| passport.field(name).unwrap_or(""));
+        wtr.write_record(std::iter::once(valid.as_str()).chain(fields))?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// reads a JSON array of passports. Any `valid` flag in the JSON is ignored
+fn read_json<R: io::Read>(reader: R) -> serde_json::Result<Vec<Passport>> {
+    serde_json::from_reader(reader)
+}
+
+/// returns `passports` in the original batch file format, with passports separated by blank lines
+fn to_batch(passports: &[Passport]) -> String {
+    passports
+        .iter()
+        .map(|passport| passport.to_batch_line())
+        .collect::<Vec<String>>()
+        .join("\n\n")
+}
+
+fn count_valid() {
     let mut valid_count: usize = 0;
-    let mut passport = Passport::new();
-
-    for line in read_lines("../input/04-input.txt").unwrap() {
-        let s = line.unwrap();
-        if !s.is_empty() {
-            parse_byr_line(&s, &mut passport);
-            parse_iyr_line(&s, &mut passport);
-            parse_eyr_line(&s, &mut passport);
-            parse_hgt_line(&s, &mut passport);
-            parse_hcl_line(&s, &mut passport);
-            parse_ecl_line(&s, &mut passport);
-            parse_pid_line(&s, &mut passport);
-            parse_cid_line(&s, &mut passport);
-        } else {
-            // hit a blank line, check current passport for validity
-            if passport.is_valid() {
-                println!("   VALID {:?}", &passport);
-                valid_count += 1;
-            }
-            passport = Passport::new();
+
+    for record in read_records("../input/04-input.txt").unwrap() {
+        let passport = parse_passport(&record);
+        if passport.is_valid() {
+            println!("   VALID {:?}", &passport);
+            valid_count += 1;
         }
     }
 
-    if passport.is_valid() {
-        println!("   VALID {:?}", &passport);
-        valid_count += 1;
+    println!("Total Valid Passports {}", valid_count);
+}
+
+/// parse the puzzle input into a Vector of passports
+fn parse_input() -> Vec<Passport> {
+    read_records("../input/04-input.txt")
+        .unwrap()
+        .iter()
+        .map(|record| parse_passport(record))
+        .collect()
+}
+
+/// usage:
+///   04-passport-proc                  count the valid passports in the puzzle input
+///   04-passport-proc json             export the puzzle input passports to JSON
+///   04-passport-proc csv              export the puzzle input passports to CSV
+///   04-passport-proc import <file>    read passports from a JSON file and print them in batch format
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(|a| a.as_str()) {
+        None => count_valid(),
+        Some("json") => {
+            write_json(&parse_input(), io::stdout()).expect("passports should serialize to JSON");
+            println!();
+        }
+        Some("csv") => {
+            write_csv(&parse_input(), io::stdout()).expect("passports should serialize to CSV")
+        }
+        Some("import") => {
+            let filename = args.get(1).expect("import requires a JSON file name");
+            let file = File::open(filename).expect("JSON file should be readable");
+            let passports = read_json(file).expect("file should contain a JSON array of passports");
+            println!("{}", to_batch(&passports));
+        }
+        Some(other) => {
+            eprintln!(
+                "unknown command {}, expected one of: json, csv, import",
+                other
+            );
+            std::process::exit(1);
+        }
     }
+}
 
-    println!("Total Valid Passports {}", valid_count);
+#[cfg(test)]
+mod tests {
+    use crate::{parse_passport, read_json, to_batch, write_csv, write_json};
+    use adventofcode::records::records;
+
+    const BATCH: &str = "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd
+byr:1937 iyr:2017 cid:147 hgt:183cm
+
+hcl:#cfa07d eyr:2025 pid:166559648
+iyr:2011 ecl:brn hgt:59in";
+
+    #[test]
+    fn json_export_includes_validity_and_omits_missing_fields() {
+        let passports: Vec<_> = records(BATCH.lines()).map(|r| parse_passport(&r)).collect();
+        let mut out = vec![];
+        write_json(&passports, &mut out).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json[0]["valid"], true);
+        assert_eq!(json[0]["hgt"], "183cm");
+        assert_eq!(json[1]["valid"], false);
+        assert!(json[1].get("byr").is_none());
+    }
+
+    #[test]
+    fn csv_export_has_header_and_empty_missing_columns() {
+        let passports: Vec<_> = records(BATCH.lines()).map(|r| parse_passport(&r)).collect();
+        let mut out = vec![];
+        write_csv(&passports, &mut out).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "valid,byr,iyr,eyr,hgt,hcl,ecl,pid,cid");
+        assert_eq!(
+            lines[1],
+            "true,1937,2017,2020,183cm,#fffffd,gry,860033327,147"
+        );
+        assert_eq!(lines[2], "false,,2011,2025,59in,#cfa07d,brn,166559648,");
+    }
+
+    #[test]
+    fn json_round_trips_back_to_batch_format() {
+        let passports: Vec<_> = records(BATCH.lines()).map(|r| parse_passport(&r)).collect();
+        let mut out = vec![];
+        write_json(&passports, &mut out).unwrap();
+        let imported = read_json(out.as_slice()).unwrap();
+        assert_eq!(imported, passports);
+        assert_eq!(
+            to_batch(&imported),
+            "byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:860033327 cid:147\n\n\
+             iyr:2011 eyr:2025 hgt:59in hcl:#cfa07d ecl:brn pid:166559648"
+        );
+    }
 }
@@ -0,0 +1,438 @@
+// Day 5: Binary Boarding
+// https://adventofcode.com/2020/day/5
+
+use std::env;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io;
+use std::io::BufRead;
+use std::path::Path;
+
+/// returns an iterator over the lines of the file pointed to by filename
+fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
+where
+    P: AsRef<Path>,
+{
+    let file = File::open(filename)?;
+    Ok(io::BufReader::new(file).lines())
+}
+
+/// the seating layout of an airplane. Both `rows` and `cols` must be a power of two, so that
+/// every seat can be reached by repeatedly halving the remaining region of the plane
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct PlaneLayout {
+    rows: u32,
+    cols: u32,
+}
+
+/// the largest number of seats a plane may have, which keeps the seat map small enough to build
+/// and draw
+const MAX_SEATS: u32 = 1 << 20;
+
+impl Default for PlaneLayout {
+    /// the 128 row by 8 column airplane from the puzzle
+    fn default() -> Self {
+        PlaneLayout { rows: 128, cols: 8 }
+    }
+}
+
+/// the errors that can occur while decoding a boarding pass
+#[derive(Debug, PartialEq, Eq)]
+enum PassError {
+    /// the boarding pass does not have one character per halving of the plane layout
+    BadLength { expected: usize, found: usize },
+    /// the boarding pass has an unexpected character at the given (zero based) position
+    BadChar { ch: char, pos: usize },
+}
+
+impl Display for PassError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PassError::BadLength { expected, found } => write!(
+                f,
+                "boarding pass should have {} characters but has {}",
+                expected, found
+            ),
+            PassError::BadChar { ch, pos } => write!(
+                f,
+                "boarding pass has an invalid character {:?} at position {}",
+                ch, pos
+            ),
+        }
+    }
+}
+
+impl PlaneLayout {
+    /// returns a new PlaneLayout, or an error if `rows` or `cols` is not a power of two, or if
+    /// the plane has more than `MAX_SEATS` seats
+    fn new(rows: u32, cols: u32) -> Result<Self, &'static str> {
+        if !rows.is_power_of_two() || !cols.is_power_of_two() {
+            Err("plane rows and cols must be a power of two")
+        } else if rows.checked_mul(cols).is_none_or(|seats| seats > MAX_SEATS) {
+            Err("plane has too many seats, rows * cols must be at most 1048576")
+        } else {
+            Ok(PlaneLayout { rows, cols })
+        }
+    }
+
+    /// the number of F/B characters needed to select a row
+    fn row_chars(&self) -> usize {
+        self.rows.trailing_zeros() as usize
+    }
+
+    /// the number of L/R characters needed to select a column
+    fn col_chars(&self) -> usize {
+        self.cols.trailing_zeros() as usize
+    }
+
+    /// decodes a boarding pass into a `(row, col)` pair. The first `row_chars` characters
+    /// of the pass select the row and the remaining `col_chars` characters select the column
+    fn decode(&self, pass: &str) -> Result<(u32, u32), PassError> {
+        let expected = self.row_chars() + self.col_chars();
+        let found = pass.chars().count();
+        if found != expected {
+            return Err(PassError::BadLength { expected, found });
+        }
+        // split on chars rather than bytes, a pass may contain (invalid) multi-byte characters
+        let mut chars = pass.chars();
+        let row_part: String = chars.by_ref().take(self.row_chars()).collect();
+        let col_part: String = chars.collect();
+        let row = decode_binary(&row_part, 'F', 'B', 0)?;
+        let col = decode_binary(&col_part, 'L', 'R', self.row_chars())?;
+        Ok((row, col))
+    }
+
+    /// encodes a `(row, col)` pair into its boarding pass string, or returns `None` if the seat
+    /// is not within this plane layout
+    fn encode(&self, row: u32, col: u32) -> Option<String> {
+        if row >= self.rows || col >= self.cols {
+            return None;
+        }
+        Some(
+            encode_binary(row, self.row_chars(), 'F', 'B')
+                + &encode_binary(col, self.col_chars(), 'L', 'R'),
+        )
+    }
+
+    /// encodes a seat id into its boarding pass string, or returns `None` if the seat
+    /// is not within this plane layout
+    fn encode_seat_id(&self, seat_id: u32) -> Option<String> {
+        self.encode(seat_id / self.cols, seat_id % self.cols)
+    }
+
+    fn seat_id(&self, row: u32, col: u32) -> u32 {
+        row * self.cols + col
+    }
+}
+
+/// treats `s` as a binary number where the `zero` character is a 0 bit and the `one` character
+/// is a 1 bit. `offset` is the position of `s` within the boarding pass, and is only used
+/// for error reporting
+fn decode_binary(s: &str, zero: char, one: char, offset: usize) -> Result<u32, PassError> {
+    s.chars().enumerate().try_fold(0, |n, (i, ch)| match ch {
+        c if c == zero => Ok(n << 1),
+        c if c == one => Ok(n << 1 | 1),
+        _ => Err(PassError::BadChar {
+            ch,
+            pos: offset + i,
+        }),
+    })
+}
+
+/// encodes the lowest `len` bits of `n` as a string of `zero` and `one` characters,
+/// most significant bit first
+fn encode_binary(n: u32, len: usize, zero: char, one: char) -> String {
+    (0..len)
+        .rev()
+        .map(|bit| if n >> bit & 1 == 1 { one } else { zero })
+        .collect()
+}
+
+/// the state of a single seat on the seat map
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum SeatState {
+    /// at least one boarding pass was found for the seat
+    Occupied,
+    /// no boarding pass was found, but the seat lies between occupied seats
+    Missing,
+    /// the seat is in front of the first, or behind the last, occupied seat, and so does not exist
+    /// on this aircraft
+    Nonexistent,
+}
+
+impl Display for SeatState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SeatState::Occupied => write!(f, "#"),
+            SeatState::Missing => write!(f, "."),
+            SeatState::Nonexistent => write!(f, " "),
+        }
+    }
+}
+
+/// every seat of a plane, along with the number of boarding passes found for that seat
+#[derive(Debug)]
+struct SeatMap {
+    layout: PlaneLayout,
+    // pass_counts[seat_id] is the number of boarding passes for seat_id
+    pass_counts: Vec<u32>,
+    // the lowest and highest occupied seat ids, None if no seats are occupied
+    occupied_range: Option<(u32, u32)>,
+}
+
+impl SeatMap {
+    /// builds a SeatMap from the (decoded) `seat_ids` of all boarding passes
+    fn new(layout: PlaneLayout, seat_ids: &[u32]) -> Self {
+        let mut pass_counts = vec![0; (layout.rows * layout.cols) as usize];
+        for &id in seat_ids {
+            pass_counts[id as usize] += 1;
+        }
+        let occupied_range = seat_ids
+            .iter()
+            .min()
+            .zip(seat_ids.iter().max())
+            .map(|(&lo, &hi)| (lo, hi));
+        SeatMap {
+            layout,
+            pass_counts,
+            occupied_range,
+        }
+    }
+
+    fn state(&self, seat_id: u32) -> SeatState {
+        match self.occupied_range {
+            _ if self.pass_counts[seat_id as usize] > 0 => SeatState::Occupied,
+            Some((lo, hi)) if seat_id > lo && seat_id < hi => SeatState::Missing,
+            _ => SeatState::Nonexistent,
+        }
+    }
+
+    /// returns `(seat_id, pass_count)` of every seat that has more than one boarding pass
+    fn duplicates(&self) -> Vec<(u32, u32)> {
+        (0..self.pass_counts.len() as u32)
+            .map(|id| (id, self.pass_counts[id as usize]))
+            .filter(|&(_, count)| count > 1)
+            .collect()
+    }
+
+    /// returns the ids of the missing seats whose neighbors on both sides are occupied
+    fn candidate_seats(&self) -> Vec<u32> {
+        (0..self.pass_counts.len() as u32)
+            .filter(|&id| self.state(id) == SeatState::Missing)
+            .filter(|&id| {
+                self.state(id - 1) == SeatState::Occupied
+                    && self.state(id + 1) == SeatState::Occupied
+            })
+            .collect()
+    }
+}
+
+impl Display for SeatMap {
+    /// draws one line per row of the plane, front row first
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for row in 0..self.layout.rows {
+            write!(f, "{:>4} |", row)?;
+            for col in 0..self.layout.cols {
+                write!(f, "{}", self.state(self.layout.seat_id(row, col)))?;
+            }
+            writeln!(f, "|")?;
+        }
+        Ok(())
+    }
+}
+
+/// parses the `rows` and `cols` arguments into a PlaneLayout
+fn parse_layout(rows: &str, cols: &str) -> Result<PlaneLayout, String> {
+    let parse = |arg: &str| -> Result<u32, String> {
+        arg.parse()
+            .map_err(|_| format!("rows and cols should be integers, found {:?}", arg))
+    };
+    Ok(PlaneLayout::new(parse(rows)?, parse(cols)?)?)
+}
+
+/// usage: 05-binary-boarding [<rows> <cols>]
+/// the plane layout defaults to 128 rows by 8 columns
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let layout = match args.as_slice() {
+        [] => Ok(PlaneLayout::default()),
+        [rows, cols] => parse_layout(rows, cols),
+        _ => Err(format!(
+            "unknown arguments {:?}, expected: [<rows> <cols>]",
+            args
+        )),
+    };
+    let layout = layout.unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let seats: Vec<u32> = read_lines("../input/05-input.txt")
+        .unwrap()
+        .map(|lines| {
+            let line = lines.unwrap();
+            let (row, col) = layout
+                .decode(&line)
+                .unwrap_or_else(|e| panic!("invalid boarding pass {}: {}", line, e));
+            layout.seat_id(row, col)
+        })
+        .collect();
+
+    // part one
+    let max_seat_id = seats
+        .iter()
+        .max()
+        .expect("at least one boarding pass string");
+    println!("max seat id is {}", max_seat_id);
+
+    // part two
+    let seat_map = SeatMap::new(layout, &seats);
+    println!("seat map: # = occupied, . = missing, blank = seat does not exist");
+    print!("{}", seat_map);
+
+    for (seat_id, count) in seat_map.duplicates() {
+        println!(
+            "duplicate boarding pass {} for seat {} found {} times",
+            layout.encode_seat_id(seat_id).unwrap(),
+            seat_id,
+            count
+        );
+    }
+    for seat_id in seat_map.candidate_seats() {
+        println!(
+            "your seat is {} with boarding pass {}",
+            seat_id,
+            layout.encode_seat_id(seat_id).unwrap()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{decode_binary, PassError, PlaneLayout, SeatMap, SeatState};
+
+    #[test]
+    fn decode_binary_row() {
+        assert_eq!(decode_binary("FBFBBFF", 'F', 'B', 0), Ok(44));
+    }
+
+    #[test]
+    fn decode_binary_col() {
+        assert_eq!(decode_binary("RLR", 'L', 'R', 0), Ok(5));
+    }
+
+    #[test]
+    fn decode_binary_col_all_r() {
+        assert_eq!(decode_binary("RRR", 'L', 'R', 0), Ok(7));
+    }
+
+    #[test]
+    fn decode_binary_col_all_l() {
+        assert_eq!(decode_binary("LLL", 'L', 'R', 0), Ok(0));
+    }
+
+    #[test]
+    fn seat_id_test() {
+        assert_eq!(PlaneLayout::default().seat_id(44, 5), 357);
+    }
+
+    #[test]
+    fn decode_default_layout() {
+        let layout = PlaneLayout::default();
+        assert_eq!(layout.decode("FBFBBFFRLR"), Ok((44, 5)));
+        assert_eq!(layout.decode("BBFFBBFRLL"), Ok((102, 4)));
+    }
+
+    #[test]
+    fn decode_other_layout() {
+        let layout = PlaneLayout::new(16, 4).unwrap();
+        assert_eq!(layout.decode("BFFBRL"), Ok((9, 2)));
+        assert_eq!(layout.seat_id(9, 2), 38);
+    }
+
+    #[test]
+    fn decode_rejects_wrong_pass_length() {
+        let layout = PlaneLayout::new(16, 4).unwrap();
+        assert_eq!(
+            layout.decode("FBFBBFFRLR"),
+            Err(PassError::BadLength {
+                expected: 6,
+                found: 10
+            })
+        );
+    }
+
+    #[test]
+    fn layout_must_be_power_of_two() {
+        assert!(PlaneLayout::new(100, 8).is_err());
+        assert!(PlaneLayout::new(128, 6).is_err());
+        assert!(PlaneLayout::new(1, 1).is_ok());
+        assert!(PlaneLayout::new(65536, 65536).is_err());
+        assert!(PlaneLayout::new(2048, 1024).is_err());
+        assert!(PlaneLayout::new(1024, 1024).is_ok());
+    }
+
+    #[test]
+    fn decode_rejects_invalid_characters() {
+        let layout = PlaneLayout::default();
+        assert_eq!(
+            layout.decode("FBFXBFFRLR"),
+            Err(PassError::BadChar { ch: 'X', pos: 3 })
+        );
+        assert_eq!(
+            layout.decode("FBFBBFFRLF"),
+            Err(PassError::BadChar { ch: 'F', pos: 9 })
+        );
+        assert_eq!(
+            layout.decode("FBFBBFF\u{e9}LR"),
+            Err(PassError::BadChar {
+                ch: '\u{e9}',
+                pos: 7
+            })
+        );
+    }
+
+    #[test]
+    fn encode_seat() {
+        let layout = PlaneLayout::default();
+        assert_eq!(layout.encode(44, 5), Some("FBFBBFFRLR".to_string()));
+        assert_eq!(layout.encode_seat_id(357), Some("FBFBBFFRLR".to_string()));
+        assert_eq!(layout.encode(128, 0), None);
+        assert_eq!(layout.encode_seat_id(1024), None);
+    }
+
+    #[test]
+    fn every_seat_round_trips() {
+        let layout = PlaneLayout::default();
+        for seat_id in 0..1024 {
+            let pass = layout.encode_seat_id(seat_id).unwrap();
+            let (row, col) = layout.decode(&pass).unwrap();
+            assert_eq!(layout.seat_id(row, col), seat_id, "pass {}", pass);
+        }
+    }
+
+    #[test]
+    fn seat_map_states_duplicates_and_candidates() {
+        let layout = PlaneLayout::new(4, 2).unwrap();
+        // seat 0 is in front of the first occupied seat, seats 3 and 5 are missing
+        let map = SeatMap::new(layout, &[1, 2, 4, 4, 6]);
+        assert_eq!(map.state(0), SeatState::Nonexistent);
+        assert_eq!(map.state(1), SeatState::Occupied);
+        assert_eq!(map.state(3), SeatState::Missing);
+        assert_eq!(map.state(7), SeatState::Nonexistent);
+        assert_eq!(map.duplicates(), vec![(4, 2)]);
+        assert_eq!(map.candidate_seats(), vec![3, 5]);
+        assert_eq!(
+            map.to_string(),
+            "   0 | #|\n   1 |#.|\n   2 |#.|\n   3 |# |\n"
+        );
+    }
+
+    #[test]
+    fn seat_map_without_passes_has_no_seats() {
+        let map = SeatMap::new(PlaneLayout::new(2, 2).unwrap(), &[]);
+        assert_eq!(map.state(1), SeatState::Nonexistent);
+        assert!(map.candidate_seats().is_empty());
+    }
+}
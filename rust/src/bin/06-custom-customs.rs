@@ -1,23 +1,284 @@
-use std::fs::File;
-use std::io;
-use std::io::BufRead;
+// Day 6: Custom Customs
+// https://adventofcode.com/2020/day/6
 
-fn main() -> Result<(), std::io::Error> {
-    let file = File::open("../input/06-input.txt")?;
-
-    let groups: Vec<Vec<String>> =
-        io::BufReader::new(file)
-            .lines()
-            .map(|l| l.unwrap())
-            .fold(vec![vec![]], |mut grp, line| {
-                match line.len() {
-                    0 => grp.push(vec![]),
-                    _ => grp.last_mut().unwrap().push(line),
+use adventofcode::records::read_records;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+/// the answers of a single person, as a bitset. Bit 0 is set if question 'a' was answered "yes",
+/// bit 1 for question 'b' ... up to bit 25 for question 'z'
+type Answers = u32;
+
+/// converts one line of a customs form into a bitset of the questions answered "yes".
+/// Any characters other than 'a'..='z' are ignored
+fn answers(line: &str) -> Answers {
+    line.bytes()
+        .filter(u8::is_ascii_lowercase)
+        .fold(0, |set, b| set | 1 << (b - b'a'))
+}
+
+/// count of questions to which *anyone* in the `group` answered "yes"
+fn union_count(group: &[String]) -> u32 {
+    group
+        .iter()
+        .fold(0, |set, line| set | answers(line))
+        .count_ones()
+}
+
+/// count of questions to which *everyone* in the `group` answered "yes"
+fn intersection_count(group: &[String]) -> u32 {
+    group
+        .iter()
+        .fold(!0, |set, line| set & answers(line))
+        .count_ones()
+}
+
+/// width, in characters, of the longest bar of the group size histogram
+const HISTOGRAM_WIDTH: usize = 50;
+
+/// popularity of a single question across all groups
+#[derive(Debug, PartialEq, Serialize)]
+struct QuestionStats {
+    question: char,
+    /// number of people who answered "yes"
+    people: usize,
+    /// number of groups in which anyone answered "yes"
+    groups_any: usize,
+    /// number of groups in which everyone answered "yes"
+    groups_all: usize,
+}
+
+/// the size of the largest (or smallest) group, along with the (zero based) indices of all groups
+/// that have that size
+#[derive(Debug, PartialEq, Serialize)]
+struct GroupExtreme {
+    size: usize,
+    groups: Vec<usize>,
+}
+
+/// summary statistics of the customs declaration answers of all groups
+#[derive(Debug, Serialize)]
+struct Report {
+    groups: usize,
+    people: usize,
+    union_sum: u32,
+    intersection_sum: u32,
+    questions: Vec<QuestionStats>,
+    largest_group: Option<GroupExtreme>,
+    smallest_group: Option<GroupExtreme>,
+    /// questions that no one in any group answered "yes" to
+    unanswered: Vec<char>,
+    /// maps a group size to the number of groups of that size
+    group_sizes: BTreeMap<usize, usize>,
+}
+
+impl Report {
+    fn new(groups: &[Vec<String>]) -> Self {
+        let questions = (b'a'..=b'z')
+            .map(|b| {
+                let bit: Answers = 1 << (b - b'a');
+                let group_has = |g: &Vec<String>, all: bool| {
+                    let mut members = g.iter().map(|line| answers(line) & bit != 0);
+                    if all {
+                        members.all(|yes| yes)
+                    } else {
+                        members.any(|yes| yes)
+                    }
+                };
+                QuestionStats {
+                    question: b as char,
+                    people: groups
+                        .iter()
+                        .flatten()
+                        .filter(|line| answers(line) & bit != 0)
+                        .count(),
+                    groups_any: groups.iter().filter(|g| group_has(g, false)).count(),
+                    groups_all: groups.iter().filter(|g| group_has(g, true)).count(),
                 }
-                grp
-            });
+            })
+            .collect::<Vec<QuestionStats>>();
+
+        let mut group_sizes = BTreeMap::new();
+        for g in groups {
+            *group_sizes.entry(g.len()).or_insert(0) += 1;
+        }
+        let extreme = |size: Option<&usize>| {
+            size.map(|&size| GroupExtreme {
+                size,
+                groups: (0..groups.len())
+                    .filter(|&i| groups[i].len() == size)
+                    .collect(),
+            })
+        };
 
-    dbg!(&groups);
+        Report {
+            groups: groups.len(),
+            people: groups.iter().map(|g| g.len()).sum(),
+            union_sum: groups.iter().map(|g| union_count(g)).sum(),
+            intersection_sum: groups.iter().map(|g| intersection_count(g)).sum(),
+            unanswered: questions
+                .iter()
+                .filter(|q| q.people == 0)
+                .map(|q| q.question)
+                .collect(),
+            questions,
+            largest_group: extreme(group_sizes.keys().next_back()),
+            smallest_group: extreme(group_sizes.keys().next()),
+            group_sizes,
+        }
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "groups: {}  people: {}", self.groups, self.people)?;
+        writeln!(f, "sum of anyone yes:   {}", self.union_sum)?;
+        writeln!(f, "sum of everyone yes: {}", self.intersection_sum)?;
+        writeln!(f)?;
+        writeln!(f, "question  people  groups_any  groups_all")?;
+        for q in &self.questions {
+            writeln!(
+                f,
+                "{:>8}  {:>6}  {:>10}  {:>10}",
+                q.question, q.people, q.groups_any, q.groups_all
+            )?;
+        }
+        writeln!(f)?;
+        for (label, extreme) in [
+            ("largest", &self.largest_group),
+            ("smallest", &self.smallest_group),
+        ] {
+            if let Some(extreme) = extreme {
+                writeln!(
+                    f,
+                    "{} group size {}: groups {:?}",
+                    label, extreme.size, extreme.groups
+                )?;
+            }
+        }
+        let unanswered: String = self.unanswered.iter().collect();
+        writeln!(f, "questions nobody answered: {:?}", unanswered)?;
+        writeln!(f)?;
+        writeln!(f, "group size histogram")?;
+        // scale the bars so that the most common group size is HISTOGRAM_WIDTH stars wide
+        let max_count = self.group_sizes.values().copied().max().unwrap_or(1);
+        for (size, count) in &self.group_sizes {
+            let bar = (count * HISTOGRAM_WIDTH).div_ceil(max_count);
+            writeln!(f, "{:>4} {:>5} {}", size, count, "*".repeat(bar))?;
+        }
+        Ok(())
+    }
+}
+
+/// usage:
+///   06-custom-customs           print the sums of the "anyone" and "everyone" yes counts
+///   06-custom-customs report    print a table of answer statistics
+///   06-custom-customs json      print the answer statistics as JSON
+fn main() -> Result<(), std::io::Error> {
+    let groups: Vec<Vec<String>> = read_records("../input/06-input.txt")?;
+
+    match env::args().nth(1).as_deref() {
+        None => {
+            let union_sum: u32 = groups.iter().map(|g| union_count(g)).sum();
+            println!("sum of questions anyone answered yes = {}", union_sum);
+
+            let intersection_sum: u32 = groups.iter().map(|g| intersection_count(g)).sum();
+            println!(
+                "sum of questions everyone answered yes = {}",
+                intersection_sum
+            );
+        }
+        Some("report") => print!("{}", Report::new(&groups)),
+        Some("json") => {
+            let json = serde_json::to_string_pretty(&Report::new(&groups))
+                .expect("report should serialize to JSON");
+            println!("{}", json);
+        }
+        Some(other) => {
+            eprintln!("unknown command {}, expected one of: report, json", other);
+            std::process::exit(1);
+        }
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{answers, intersection_count, union_count, GroupExtreme, Report};
+    use adventofcode::records::records;
+
+    const EXAMPLE: &str = "abc
+
+a
+b
+c
+
+ab
+ac
+
+a
+a
+a
+a
+
+b";
+
+    #[test]
+    fn answers_sets_one_bit_per_question() {
+        assert_eq!(answers("abz"), 0b10_0000_0000_0000_0000_0000_0011);
+        assert_eq!(answers(""), 0);
+    }
+
+    #[test]
+    fn union_counts_of_example() {
+        let counts: Vec<u32> = records(EXAMPLE.lines()).map(|g| union_count(&g)).collect();
+        assert_eq!(counts, vec![3, 3, 3, 1, 1]);
+    }
+
+    #[test]
+    fn intersection_counts_of_example() {
+        let counts: Vec<u32> = records(EXAMPLE.lines())
+            .map(|g| intersection_count(&g))
+            .collect();
+        assert_eq!(counts, vec![3, 0, 1, 1, 1]);
+    }
+
+    #[test]
+    fn report_of_example() {
+        let groups: Vec<Vec<String>> = records(EXAMPLE.lines()).collect();
+        let report = Report::new(&groups);
+        assert_eq!(report.groups, 5);
+        assert_eq!(report.people, 11);
+        assert_eq!(report.union_sum, 11);
+        assert_eq!(report.intersection_sum, 6);
+
+        let a = &report.questions[0];
+        assert_eq!((a.people, a.groups_any, a.groups_all), (8, 4, 3));
+        let c = &report.questions[2];
+        assert_eq!((c.people, c.groups_any, c.groups_all), (3, 3, 1));
+
+        assert_eq!(
+            report.largest_group,
+            Some(GroupExtreme {
+                size: 4,
+                groups: vec![3]
+            })
+        );
+        assert_eq!(
+            report.smallest_group,
+            Some(GroupExtreme {
+                size: 1,
+                groups: vec![0, 4]
+            })
+        );
+        assert_eq!(report.unanswered, ('d'..='z').collect::<Vec<char>>());
+        assert_eq!(
+            report.group_sizes.into_iter().collect::<Vec<_>>(),
+            vec![(1, 2), (2, 1), (3, 1), (4, 1)]
+        );
+    }
+}
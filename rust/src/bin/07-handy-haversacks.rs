@@ -2,10 +2,13 @@
 // https://adventofcode.com/2020/day/7
 
 use lazy_static::lazy_static;
+use num_bigint::BigUint;
 use regex::Regex;
 use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fmt;
+use std::fmt::{Display, Formatter};
 use std::fs::File;
-use std::hash::{Hash, Hasher};
 use std::io;
 use std::io::BufRead;
 use std::path::Path;
@@ -13,30 +16,318 @@ use std::path::Path;
 #[derive(Debug, Clone)]
 struct Bag {
     // number of bags contained, = 0 if this bag is a container bag
-    amount: i32,
+    amount: u32,
     // name of the container bag, or name of the bag being contained
     name: String,
 }
 
 impl Bag {
-    fn new(amount: i32, name: String) -> Self {
+    fn new(amount: u32, name: String) -> Self {
         Bag { amount, name }
     }
 }
 
-// Bags are equal if their names are equal
-impl PartialEq for Bag {
-    fn eq(&self, other: &Self) -> bool {
-        self.name == other.name
+/// index of an interned bag color within a `BagGraph`
+type ColorId = usize;
+
+/// the problems that can be found when validating the bag rules. Line numbers start at 1
+#[derive(Debug, PartialEq, Eq)]
+enum RuleError {
+    /// a line that is not a bag rule
+    Malformed { line: usize, text: String },
+    /// more than one rule was given for the same bag color
+    DuplicateRule {
+        color: String,
+        first_line: usize,
+        line: usize,
+    },
+    /// a rule refers to a bag color that has no rule of its own
+    UndefinedColor { color: String, line: usize },
+    /// a bag color eventually contains itself. The path starts and ends with the same color
+    Cycle(Vec<String>),
+}
+
+impl Display for RuleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleError::Malformed { line, text } => {
+                write!(f, "line {}: {:?} is not a bag rule", line, text)
+            }
+            RuleError::DuplicateRule {
+                color,
+                first_line,
+                line,
+            } => write!(
+                f,
+                "line {}: duplicate rule for {} bags, first defined on line {}",
+                line, color, first_line
+            ),
+            RuleError::UndefinedColor { color, line } => {
+                write!(f, "line {}: {} bags have no rule", line, color)
+            }
+            RuleError::Cycle(path) => write!(f, "bags contain themselves: {}", path.join(" -> ")),
+        }
+    }
+}
+
+/// returned when querying a bag color that does not appear in any rule
+#[derive(Debug, PartialEq, Eq)]
+struct UnknownColor(String);
+
+impl Display for UnknownColor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "there are no rules for {} bags", self.0)
     }
 }
 
-impl Eq for Bag {}
+/// a directed graph of the bag rules. Each bag color is interned to a `ColorId`, and the
+/// edges are weighted by the number of bags contained
+#[derive(Debug, Default)]
+struct BagGraph {
+    // maps a ColorId to its color name
+    colors: Vec<String>,
+    // maps a color name to its ColorId
+    ids: HashMap<String, ColorId>,
+    // contains[c] holds (amount, inner color) of each bag directly inside color c
+    contains: Vec<Vec<(u32, ColorId)>>,
+    // contained_in[c] holds (amount, outer color) of each bag that directly holds color c
+    contained_in: Vec<Vec<(u32, ColorId)>>,
+    // rule_line[c] is the line number of the rule for color c, None if c has no rule
+    rule_line: Vec<Option<usize>>,
+}
+
+impl BagGraph {
+    /// builds a BagGraph from lines of bag rules. Blank lines are ignored.
+    /// All problems found in the rules are returned, see `RuleError`
+    fn from_rules<I, S>(lines: I) -> Result<Self, Vec<RuleError>>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut graph = BagGraph::default();
+        let mut errors = vec![];
+        for (idx, line) in lines.into_iter().enumerate() {
+            let line = line.as_ref();
+            if line.trim().is_empty() {
+                continue;
+            }
+            let container = match parse_container_bag(line) {
+                Some(container) => container,
+                None => {
+                    errors.push(RuleError::Malformed {
+                        line: idx + 1,
+                        text: line.to_string(),
+                    });
+                    continue;
+                }
+            };
+            let contained = parse_contained_bags(line).unwrap_or_default();
+            if let Err(e) = graph.add_rule(idx + 1, &container.name, &contained) {
+                errors.push(e);
+            }
+        }
+        errors.extend(graph.validate());
+        if errors.is_empty() {
+            Ok(graph)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// returns the ColorId of `color`, adding the color to the graph if needed
+    fn intern(&mut self, color: &str) -> ColorId {
+        if let Some(&id) = self.ids.get(color) {
+            return id;
+        }
+        let id = self.colors.len();
+        self.colors.push(color.to_string());
+        self.ids.insert(color.to_string(), id);
+        self.contains.push(vec![]);
+        self.contained_in.push(vec![]);
+        self.rule_line.push(None);
+        id
+    }
+
+    /// returns the ColorId of `color`, or an error if `color` is not in the graph
+    fn id(&self, color: &str) -> Result<ColorId, UnknownColor> {
+        self.ids
+            .get(color)
+            .copied()
+            .ok_or_else(|| UnknownColor(color.to_string()))
+    }
+
+    /// adds the rule found on `line` to the graph, unless `container` already has a rule
+    fn add_rule(
+        &mut self,
+        line: usize,
+        container: &str,
+        contained: &[Bag],
+    ) -> Result<(), RuleError> {
+        let outer = self.intern(container);
+        if let Some(first_line) = self.rule_line[outer] {
+            return Err(RuleError::DuplicateRule {
+                color: container.to_string(),
+                first_line,
+                line,
+            });
+        }
+        self.rule_line[outer] = Some(line);
+        for bag in contained {
+            let inner = self.intern(&bag.name);
+            self.contains[outer].push((bag.amount, inner));
+            self.contained_in[inner].push((bag.amount, outer));
+        }
+        Ok(())
+    }
+
+    /// checks that every color has a rule and that no color contains itself
+    fn validate(&self) -> Vec<RuleError> {
+        let mut errors = vec![];
+        for (id, line) in self.rule_line.iter().enumerate() {
+            if line.is_none() {
+                // report the first rule that refers to the undefined color
+                let line = self.contained_in[id]
+                    .iter()
+                    .filter_map(|&(_, outer)| self.rule_line[outer])
+                    .min()
+                    .unwrap_or(0);
+                errors.push(RuleError::UndefinedColor {
+                    color: self.colors[id].clone(),
+                    line,
+                });
+            }
+        }
+        for cycle in self.find_cycles() {
+            let path = cycle.iter().map(|&id| self.colors[id].clone()).collect();
+            errors.push(RuleError::Cycle(path));
+        }
+        errors
+    }
+
+    /// returns every cycle found by a depth first search of the graph. Each cycle is a path of
+    /// colors that starts and ends with the same color. The search is iterative so that
+    /// very deep rule sets cannot overflow the stack
+    fn find_cycles(&self) -> Vec<Vec<ColorId>> {
+        #[derive(Copy, Clone, PartialEq)]
+        enum Mark {
+            New,
+            OnPath,
+            Done,
+        }
+        let mut marks = vec![Mark::New; self.colors.len()];
+        let mut cycles = vec![];
+        for start in 0..self.colors.len() {
+            if marks[start] != Mark::New {
+                continue;
+            }
+            // the current search path, holding (color, index of the next edge to follow)
+            let mut path: Vec<(ColorId, usize)> = vec![(start, 0)];
+            marks[start] = Mark::OnPath;
+            while let Some(top) = path.last_mut() {
+                let (color, edge) = *top;
+                top.1 += 1;
+                match self.contains[color].get(edge) {
+                    Some(&(_, inner)) => match marks[inner] {
+                        Mark::New => {
+                            marks[inner] = Mark::OnPath;
+                            path.push((inner, 0));
+                        }
+                        Mark::OnPath => {
+                            let pos = path.iter().position(|&(c, _)| c == inner).unwrap();
+                            let mut cycle: Vec<ColorId> =
+                                path[pos..].iter().map(|&(c, _)| c).collect();
+                            cycle.push(inner);
+                            cycles.push(cycle);
+                        }
+                        Mark::Done => {}
+                    },
+                    None => {
+                        marks[color] = Mark::Done;
+                        path.pop();
+                    }
+                }
+            }
+        }
+        cycles
+    }
+
+    /// returns the ColorIds of all bags that can be reached from `start` by following `edges`,
+    /// not including `start` itself (unless it is part of a cycle)
+    fn reachable(&self, start: ColorId, edges: &[Vec<(u32, ColorId)>]) -> HashSet<ColorId> {
+        let mut visited: HashSet<ColorId> = HashSet::new();
+        let mut to_visit: Vec<ColorId> = vec![start];
+        while let Some(next) = to_visit.pop() {
+            for &(_, other) in &edges[next] {
+                if visited.insert(other) {
+                    to_visit.push(other);
+                }
+            }
+        }
+        visited
+    }
+
+    /// returns the colors of all bags that can eventually contain at least one `color` bag,
+    /// sorted by name
+    fn ancestors(&self, color: &str) -> Result<Vec<&str>, UnknownColor> {
+        let visited = self.reachable(self.id(color)?, &self.contained_in);
+        let mut ancestors: Vec<&str> = visited.into_iter().map(|id| &*self.colors[id]).collect();
+        ancestors.sort_unstable();
+        Ok(ancestors)
+    }
+
+    /// returns the total number of individual bags required inside a `color` bag
+    fn total_contained(&self, color: &str) -> Result<BigUint, UnknownColor> {
+        let id = self.id(color)?;
+        Ok(self.bag_totals(id)[id].take().unwrap())
+    }
 
-// only the Bag.name is hashed
-impl Hash for Bag {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.name.hash(state)
+    /// returns the bags directly inside a `color` bag as `(amount, inner color, total)` where
+    /// total is the number of individual bags inside *one* of the inner color bags
+    fn contents(&self, color: &str) -> Result<Vec<(u32, &str, BigUint)>, UnknownColor> {
+        let id = self.id(color)?;
+        let totals = self.bag_totals(id);
+        Ok(self.contains[id]
+            .iter()
+            .map(|&(amount, inner)| {
+                let total = totals[inner].clone().unwrap();
+                (amount, &*self.colors[inner], total)
+            })
+            .collect())
+    }
+
+    /// returns the total number of bags inside each color that can be reached from `start`,
+    /// `None` for the colors that can't be reached.
+    /// Colors are summed in post order (i.e. topologically) with each color summed only once,
+    /// so that deep or wide rule sets don't recompute the same sub-totals over and over.
+    /// The graph must not contain cycles, which `from_rules` guarantees
+    fn bag_totals(&self, start: ColorId) -> Vec<Option<BigUint>> {
+        let mut totals: Vec<Option<BigUint>> = vec![None; self.colors.len()];
+        // holds (color, true if the color's inner bags have already been pushed)
+        let mut to_visit = vec![(start, false)];
+        while let Some((color, expanded)) = to_visit.pop() {
+            if totals[color].is_some() {
+                continue;
+            }
+            if expanded {
+                let total = self.contains[color]
+                    .iter()
+                    .map(|&(amount, inner)| {
+                        let inner_total =
+                            totals[inner].as_ref().expect("inner bags are summed first");
+                        BigUint::from(amount) * (inner_total + 1u32)
+                    })
+                    .sum();
+                totals[color] = Some(total);
+            } else {
+                to_visit.push((color, true));
+                for &(_, inner) in &self.contains[color] {
+                    if totals[inner].is_none() {
+                        to_visit.push((inner, false));
+                    }
+                }
+            }
+        }
+        totals
     }
 }
 
@@ -49,13 +340,13 @@ where
     Ok(io::BufReader::new(file).lines())
 }
 
-fn parse_container_bag(line: &str) -> Bag {
+fn parse_container_bag(line: &str) -> Option<Bag> {
     lazy_static! {
         static ref CONTAINER_RE: Regex = Regex::new(r"(.+?) bag[s]?").expect("valid RegEx");
     }
     // get the container bag name
-    let container: String = CONTAINER_RE.captures(line).unwrap()[1].to_string();
-    Bag::new(0, container)
+    let container: String = CONTAINER_RE.captures(line)?[1].to_string();
+    Some(Bag::new(0, container))
 }
 
 fn parse_contained_bags(line: &str) -> Option<Vec<Bag>> {
@@ -68,7 +359,7 @@ fn parse_contained_bags(line: &str) -> Option<Vec<Bag>> {
     let bags = BAGS_RE
         .captures_iter(line)
         .map(|cap| {
-            let amount: i32 = cap[1].parse().expect("valid integer for bag amount");
+            let amount: u32 = cap[1].parse().expect("valid integer for bag amount");
             let name: String = cap[2].to_string();
             Bag::new(amount, name)
         })
@@ -77,108 +368,142 @@ fn parse_contained_bags(line: &str) -> Option<Vec<Bag>> {
     Some(bags)
 }
 
-// compute count of unique bag colors can eventually contain at least one shiny gold bag
-#[allow(dead_code)]
-fn part_one() {
-    // a HashMap that maps a contained bag name, to a set of bags that contain them
-    let mut bag_map: HashMap<String, HashSet<Bag>> = HashMap::new();
-
-    // read the input file and parse it into the bag_map
-    for res_line in read_lines("../input/07-input.txt").unwrap() {
-        let line = res_line.unwrap();
-        let container = parse_container_bag(&line);
-        let contained = parse_contained_bags(&line);
+/// selects which part of a BagGraph is exported to DOT
+#[derive(Debug, Copy, Clone)]
+enum Subgraph<'a> {
+    /// every bag color
+    All,
+    /// the color and every color that it can eventually contain
+    ReachableFrom(&'a str),
+    /// the color and every color that can eventually contain it
+    LeadingTo(&'a str),
+}
 
-        // build a HashMap that maps each contained bag name, to a set of bags that contain them
-        if let Some(contained_bags) = contained {
-            for bag in contained_bags {
-                let bentry = bag_map.entry(bag.name).or_insert(HashSet::new());
-                bentry.insert(container.to_owned());
+impl BagGraph {
+    /// returns the `subgraph` of the bag rules in Graphviz DOT format. Each edge points from
+    /// the outer bag to the inner bag and is labelled with the amount of inner bags
+    fn to_dot(&self, subgraph: Subgraph) -> Result<String, UnknownColor> {
+        let included: HashSet<ColorId> = match subgraph {
+            Subgraph::All => (0..self.colors.len()).collect(),
+            Subgraph::ReachableFrom(color) => {
+                let id = self.id(color)?;
+                let mut ids = self.reachable(id, &self.contains);
+                ids.insert(id);
+                ids
             }
-        }
-    }
+            Subgraph::LeadingTo(color) => {
+                let id = self.id(color)?;
+                let mut ids = self.reachable(id, &self.contained_in);
+                ids.insert(id);
+                ids
+            }
+        };
+        // quote a color name as a DOT identifier
+        let quote = |id: ColorId| format!("\"{}\"", self.colors[id].replace('"', "\\\""));
 
-    // now determine the count of bags that can contain at least one 'shiny gold' bag
-    //
-    let mut bags_to_visit = bag_map
-        .get("shiny gold")
-        .unwrap()
-        .iter()
-        .collect::<Vec<&Bag>>();
-    let mut containing_bags: HashSet<&str> = bags_to_visit.iter().map(|&b| &*b.name).collect();
-    while !bags_to_visit.is_empty() {
-        let next = bags_to_visit.pop().unwrap();
-        //println!("next is {:?} with values {:?}", &next, &bag_map.get(&*next.name));
-        if let Some(next_bags) = bag_map.get(&*next.name) {
-            for b in next_bags {
-                containing_bags.insert(&*b.name);
-                bags_to_visit.push(b);
+        let mut dot = String::from("digraph bags {\n");
+        for id in (0..self.colors.len()).filter(|id| included.contains(id)) {
+            dot.push_str(&format!("    {};\n", quote(id)));
+        }
+        for outer in (0..self.colors.len()).filter(|id| included.contains(id)) {
+            for &(amount, inner) in &self.contains[outer] {
+                if included.contains(&inner) {
+                    dot.push_str(&format!(
+                        "    {} -> {} [label=\"{}\"];\n",
+                        quote(outer),
+                        quote(inner),
+                        amount
+                    ));
+                }
             }
         }
+        dot.push_str("}\n");
+        Ok(dot)
     }
-    //dbg!(&containing_bags);
-    println!(
-        "bag colors that can eventually contain at least one shiny gold bag = {}",
-        &containing_bags.len()
-    );
 }
 
-// parse the input file into a HashMap
-fn parse_input(filename: &str) -> HashMap<Bag, Vec<Bag>> {
-    let mut bag_map = HashMap::new();
-    for res_line in read_lines(filename).unwrap() {
-        let line = res_line.unwrap();
-        let contained = parse_contained_bags(&line);
-        if let Some(contained_bags) = contained {
-            let contained_sum = contained_bags.iter().map(|b| b.amount).sum();
-            let mut container = parse_container_bag(&line);
-            container.amount = contained_sum;
-            bag_map.insert(container, contained_bags);
-        }
-    }
-    bag_map
+// parse the input file into a BagGraph
+fn parse_input(filename: &str) -> Result<BagGraph, Vec<RuleError>> {
+    let lines = read_lines(filename).unwrap().map(|line| line.unwrap());
+    BagGraph::from_rules(lines)
 }
 
-// recursively count the amount of bags contained within `current` bag
-fn sum_bag(current: &Bag, map: &HashMap<Bag, Vec<Bag>>) -> usize {
-    let mut sum = 0;
-    if let Some(vec) = map.get(current) {
-        for next_bag in vec.iter() {
-            sum += next_bag.amount as usize + next_bag.amount as usize * sum_bag(next_bag, map);
-        }
+// compute count of unique bag colors that can eventually contain at least one `color` bag
+fn part_one(bags: &BagGraph, color: &str) -> Result<(), UnknownColor> {
+    let ancestors = bags.ancestors(color)?;
+    println!(
+        "bag colors that can eventually contain at least one {} bag = {}",
+        color,
+        ancestors.len()
+    );
+    for ancestor in ancestors {
+        println!("    {}", ancestor);
     }
-    sum
+    Ok(())
 }
 
-// how many individual bags are required inside your shiny gold bag
-fn part_two() {
-    // map input file into a hash map
-    let bags = parse_input("../input/07-input.txt");
-
-    // now determine the count of individual bags required inside a 'shiny gold' bag
-    let (bag, _) = bags
-        .get_key_value(&Bag::new(0, "shiny gold".to_string()))
-        .unwrap();
-
-    //dbg!(&bags);
-
-    let total = sum_bag(bag, &bags);
-    println!("total = {}", total);
+// how many individual bags are required inside a `color` bag
+fn part_two(bags: &BagGraph, color: &str) -> Result<(), UnknownColor> {
+    println!("total = {}", bags.total_contained(color)?);
+    for (amount, inner, total) in bags.contents(color)? {
+        println!("    {} {} bags, each holding {} bags", amount, inner, total);
+    }
+    Ok(())
 }
 
+/// usage:
+///   07-handy-haversacks [color]             answer both parts for color, default is shiny gold
+///   07-handy-haversacks dot                 print all bag rules in Graphviz DOT format
+///   07-handy-haversacks dot from <color>    print the bags that color can eventually contain
+///   07-handy-haversacks dot to <color>      print the bags that can eventually contain color
+/// a two word color can be given with or without quotes
 fn main() {
-    //part_one();
-    part_two();
+    let bags = match parse_input("../input/07-input.txt") {
+        Ok(bags) => bags,
+        Err(errors) => {
+            for e in errors {
+                eprintln!("{}", e);
+            }
+            std::process::exit(1);
+        }
+    };
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args
+        .iter()
+        .map(|a| a.as_str())
+        .collect::<Vec<&str>>()
+        .as_slice()
+    {
+        [] => part_one(&bags, "shiny gold").and_then(|_| part_two(&bags, "shiny gold")),
+        ["dot"] => bags.to_dot(Subgraph::All).map(|dot| print!("{}", dot)),
+        ["dot", "from", color @ ..] => bags
+            .to_dot(Subgraph::ReachableFrom(&color.join(" ")))
+            .map(|dot| print!("{}", dot)),
+        ["dot", "to", color @ ..] => bags
+            .to_dot(Subgraph::LeadingTo(&color.join(" ")))
+            .map(|dot| print!("{}", dot)),
+        color => {
+            let color = color.join(" ");
+            part_one(&bags, &color).and_then(|_| part_two(&bags, &color))
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{parse_contained_bags, parse_container_bag};
+    use crate::{
+        parse_contained_bags, parse_container_bag, BagGraph, RuleError, Subgraph, UnknownColor,
+    };
+    use num_bigint::BigUint;
 
     #[test]
     fn can_parse_containing_bag_name() {
         let line = "light red bags contain 1 bright white bag, 2 muted yellow bags.";
-        let bag = parse_container_bag(line);
+        let bag = parse_container_bag(line).unwrap();
         assert_eq!(bag.name, "light red");
         assert_eq!(bag.amount, 0);
     }
@@ -200,4 +525,251 @@ mod tests {
         let bags = parse_contained_bags(line);
         assert!(bags.is_none());
     }
+
+    const EXAMPLE: &str = "light red bags contain 1 bright white bag, 2 muted yellow bags.
+dark orange bags contain 3 bright white bags, 4 muted yellow bags.
+bright white bags contain 1 shiny gold bag.
+muted yellow bags contain 2 shiny gold bags, 9 faded blue bags.
+shiny gold bags contain 1 dark olive bag, 2 vibrant plum bags.
+dark olive bags contain 3 faded blue bags, 4 dotted black bags.
+vibrant plum bags contain 5 faded blue bags, 6 dotted black bags.
+faded blue bags contain no other bags.
+dotted black bags contain no other bags.";
+
+    #[test]
+    fn graph_interns_each_color_once() {
+        let graph = BagGraph::from_rules(EXAMPLE.lines()).unwrap();
+        assert_eq!(graph.colors.len(), 9);
+        let gold = graph.id("shiny gold").unwrap();
+        assert_eq!(graph.contains[gold].len(), 2);
+        assert_eq!(graph.contained_in[gold].len(), 2);
+    }
+
+    #[test]
+    fn ancestors_of_shiny_gold() {
+        let graph = BagGraph::from_rules(EXAMPLE.lines()).unwrap();
+        assert_eq!(
+            graph.ancestors("shiny gold"),
+            Ok(vec![
+                "bright white",
+                "dark orange",
+                "light red",
+                "muted yellow"
+            ])
+        );
+        assert_eq!(graph.ancestors("light red"), Ok(vec![]));
+    }
+
+    #[test]
+    fn total_contained_in_shiny_gold() {
+        let graph = BagGraph::from_rules(EXAMPLE.lines()).unwrap();
+        assert_eq!(
+            graph.total_contained("shiny gold"),
+            Ok(BigUint::from(32u32))
+        );
+        assert_eq!(graph.total_contained("faded blue"), Ok(BigUint::from(0u32)));
+    }
+
+    #[test]
+    fn contents_of_shiny_gold() {
+        let graph = BagGraph::from_rules(EXAMPLE.lines()).unwrap();
+        assert_eq!(
+            graph.contents("shiny gold"),
+            Ok(vec![
+                (1, "dark olive", BigUint::from(7u32)),
+                (2, "vibrant plum", BigUint::from(11u32))
+            ])
+        );
+    }
+
+    #[test]
+    fn unknown_colors_are_an_error() {
+        let graph = BagGraph::from_rules(EXAMPLE.lines()).unwrap();
+        let unknown = UnknownColor("plaid purple".to_string());
+        assert_eq!(graph.ancestors("plaid purple"), Err(unknown));
+        assert!(graph.total_contained("plaid purple").is_err());
+        assert!(graph.contents("plaid purple").is_err());
+    }
+
+    #[test]
+    fn dot_export_of_reachable_subgraph() {
+        let graph = BagGraph::from_rules(EXAMPLE.lines()).unwrap();
+        let dot = graph.to_dot(Subgraph::ReachableFrom("dark olive")).unwrap();
+        assert_eq!(
+            dot,
+            "digraph bags {
+    \"faded blue\";
+    \"dark olive\";
+    \"dotted black\";
+    \"dark olive\" -> \"faded blue\" [label=\"3\"];
+    \"dark olive\" -> \"dotted black\" [label=\"4\"];
+}
+"
+        );
+    }
+
+    #[test]
+    fn dot_export_of_subgraph_leading_to_color() {
+        let graph = BagGraph::from_rules(EXAMPLE.lines()).unwrap();
+        let dot = graph.to_dot(Subgraph::LeadingTo("bright white")).unwrap();
+        assert_eq!(
+            dot,
+            "digraph bags {
+    \"light red\";
+    \"bright white\";
+    \"dark orange\";
+    \"light red\" -> \"bright white\" [label=\"1\"];
+    \"dark orange\" -> \"bright white\" [label=\"3\"];
+}
+"
+        );
+    }
+
+    #[test]
+    fn dot_export_of_all_rules() {
+        let graph = BagGraph::from_rules(EXAMPLE.lines()).unwrap();
+        let dot = graph.to_dot(Subgraph::All).unwrap();
+        assert_eq!(dot.matches(" -> ").count(), 13);
+        assert_eq!(dot.lines().count(), 2 + 9 + 13);
+        assert!(graph.to_dot(Subgraph::LeadingTo("plaid purple")).is_err());
+    }
+
+    #[test]
+    fn detects_duplicate_rules() {
+        let rules = "a b bags contain 1 c d bag.
+c d bags contain no other bags.
+a b bags contain 2 c d bags.";
+        let errors = BagGraph::from_rules(rules.lines()).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![RuleError::DuplicateRule {
+                color: "a b".to_string(),
+                first_line: 1,
+                line: 3
+            }]
+        );
+    }
+
+    #[test]
+    fn detects_undefined_colors() {
+        let rules = "a b bags contain 1 c d bag.
+e f bags contain 1 c d bag, 3 g h bags.";
+        let errors = BagGraph::from_rules(rules.lines()).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                RuleError::UndefinedColor {
+                    color: "c d".to_string(),
+                    line: 1
+                },
+                RuleError::UndefinedColor {
+                    color: "g h".to_string(),
+                    line: 2
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn detects_cycles() {
+        let rules = "a b bags contain 1 c d bag.
+c d bags contain 2 e f bags.
+e f bags contain 1 a b bag, 1 g h bag.
+g h bags contain 1 g h bag.";
+        let errors = BagGraph::from_rules(rules.lines()).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                RuleError::Cycle(vec![
+                    "a b".to_string(),
+                    "c d".to_string(),
+                    "e f".to_string(),
+                    "a b".to_string()
+                ]),
+                RuleError::Cycle(vec!["g h".to_string(), "g h".to_string()]),
+            ]
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "bags contain themselves: a b -> c d -> e f -> a b"
+        );
+    }
+
+    #[test]
+    fn detects_malformed_rules() {
+        let rules = "a b bags contain no other bags.
+hello world";
+        let errors = BagGraph::from_rules(rules.lines()).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![RuleError::Malformed {
+                line: 2,
+                text: "hello world".to_string()
+            }]
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "line 2: \"hello world\" is not a bag rule"
+        );
+    }
+
+    /// returns a unique two word color name for `n`, such as "pale bcd". Generated colors must
+    /// not contain digits, as the rule parser would take them for a bag amount
+    fn color(n: usize) -> String {
+        let mut letters = vec![];
+        let mut n = n;
+        loop {
+            letters.push((b'a' + (n % 26) as u8) as char);
+            n /= 26;
+            if n == 0 {
+                break;
+            }
+        }
+        format!("pale {}", letters.into_iter().rev().collect::<String>())
+    }
+
+    #[test]
+    fn total_contained_of_deep_rules_does_not_overflow() {
+        // the first color holds 10 of the second color, which holds 10 of the third... 100 deep
+        let mut rules: Vec<String> = (0..100)
+            .map(|i| format!("{} bags contain 10 {} bags.", color(i), color(i + 1)))
+            .collect();
+        rules.push(format!("{} bags contain no other bags.", color(100)));
+        let graph = BagGraph::from_rules(&rules).unwrap();
+        // 10 + 10^2 + ... + 10^100
+        let expected: BigUint = (1..=100u32).map(|n| BigUint::from(10u32).pow(n)).sum();
+        assert_eq!(graph.total_contained(&color(0)), Ok(expected));
+    }
+
+    #[test]
+    fn total_contained_of_wide_shared_rules_is_fast() {
+        // every bag holds 2 of the next bag and 3 of the one after that. Without memoization
+        // the number of paths through the rules grows like the fibonacci numbers
+        let n = 2000;
+        let mut rules: Vec<String> = (0..n - 2)
+            .map(|i| {
+                format!(
+                    "{} bags contain 2 {} bags, 3 {} bags.",
+                    color(i),
+                    color(i + 1),
+                    color(i + 2)
+                )
+            })
+            .collect();
+        rules.push(format!(
+            "{} bags contain 1 {} bag.",
+            color(n - 2),
+            color(n - 1)
+        ));
+        rules.push(format!("{} bags contain no other bags.", color(n - 1)));
+        let graph = BagGraph::from_rules(&rules).unwrap();
+
+        // t(i) = 2 * (t(i+1) + 1) + 3 * (t(i+2) + 1)
+        let mut totals = vec![BigUint::from(0u32); n];
+        totals[n - 2] = BigUint::from(1u32);
+        for i in (0..n - 2).rev() {
+            totals[i] = (&totals[i + 1] + 1u32) * 2u32 + (&totals[i + 2] + 1u32) * 3u32;
+        }
+        assert_eq!(graph.total_contained(&color(0)).unwrap(), totals[0]);
+    }
 }
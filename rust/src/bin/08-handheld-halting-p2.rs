@@ -1,165 +1,401 @@
 // Day 8 - Handheld Halting - Part 2
 // https://adventofcode.com/2020/day/8
 
-use lazy_static::lazy_static;
-use regex::Regex;
-use std::fs::File;
-use std::io;
-use std::io::BufRead;
+use adventofcode::cfg::Cfg;
+use adventofcode::console::{read_program, Console, Ins, Outcome};
+use std::collections::BTreeSet;
+use std::env;
 
-/// Ins represents all the possible Instructions for this challenge
-#[derive(Debug, Copy, Clone)]
-enum Ins {
-    ACC(i32),
-    JMP(i32),
-    NOP(i32),
+/// returns a Vector of indices, of NOP and JMP statements that were executed before a loop (or
+/// a jump out of bounds) occurred
+fn ins_indices(ins: &[Ins]) -> Option<Vec<usize>> {
+    let mut console = Console::new(ins.to_vec());
+    match console.run_until_loop_or_halt() {
+        // program terminated
+        Outcome::Halted(_) => None,
+        _ => Some(
+            console
+                .executed()
+                .iter()
+                .copied()
+                .filter(|&i| !Ins::is_acc(&ins[i]))
+                .collect(),
+        ),
+    }
 }
 
-impl Ins {
-    fn from(ins: &str, amount: i32) -> Self {
-        match ins {
-            "acc" => Ins::ACC(amount),
-            "jmp" => Ins::JMP(amount),
-            _ => Ins::NOP(amount),
-        }
+/// if the program given by `ins` terminates, `Some(i64)` is returned containing the final
+/// accumulator value. If the program loops or jumps out of bounds, `None` is returned
+fn terminates(ins: &[Ins]) -> Option<i64> {
+    match Console::new(ins.to_vec()).run_until_loop_or_halt() {
+        Outcome::Halted(acc) => Some(acc),
+        _ => None,
     }
+}
 
-    /// swaps a NOP to a JMP and a JMP to a NOP. ACC returns itself
-    fn swap(ins: &Ins) -> Self {
-        match ins {
-            Ins::NOP(amt) => Ins::JMP(*amt),
-            Ins::JMP(amt) => Ins::NOP(*amt),
-            Ins::ACC(_amt) => *ins,
+/// finds the single JMP or NOP to swap that makes the program terminate, by trying each executed
+/// JMP and NOP in turn, in execution order, and re-running the whole program.
+/// Returns `(swapped index, final acc)` for the first such swap on the execution path, or `None`
+/// if no single swap makes the program terminate (or the program already terminates).
+/// This takes quadratic time, see `repair` for a linear time approach
+fn brute_force_repair(ins: &[Ins]) -> Option<(usize, i64)> {
+    let mut ins = ins.to_vec();
+    for idx in ins_indices(&ins)? {
+        ins[idx] = Ins::swap(&ins[idx]);
+        if let Some(acc) = terminates(&ins) {
+            return Some((idx, acc));
         }
+        // restore the swapped instruction and try the next one
+        ins[idx] = Ins::swap(&ins[idx]);
     }
+    None
+}
 
-    /// returns true if `ins` is an ACC instruction
-    fn is_acc(ins: &Ins) -> bool {
-        match ins {
-            Ins::ACC(_) => true,
-            _ => false,
-        }
+/// finds the single JMP or NOP to swap that makes the program terminate, in linear time, using
+/// the control-flow graph of the program. Returns `(swapped index, final acc)` for the first
+/// such swap on the execution path, or `None` if no single swap makes the program terminate
+/// (or the program already terminates)
+fn repair(ins: &[Ins]) -> Option<(usize, i64)> {
+    let ip = *Cfg::new(ins).terminating_flips().first()?;
+    let mut repaired = ins.to_vec();
+    repaired[ip] = Ins::swap(&ins[ip]);
+    match Console::new(repaired).run_until_loop_or_halt() {
+        Outcome::Halted(acc) => Some((ip, acc)),
+        _ => unreachable!("swapped program should terminate"),
     }
 }
 
-/// parse input file into a Vector of `Ins`tructions
-fn parse_input(filename: &str) -> Vec<Ins> {
-    lazy_static! {
-        static ref RE: Regex =
-            Regex::new(r"(?P<ins>\w+) (?P<sign>[+-])(?P<amount>\d+)").expect("valid RegEx");
-    }
-    let file = File::open(filename).unwrap();
-    io::BufReader::new(file)
-        .lines()
-        .map(|l| l.unwrap())
-        .map(|line| {
-            let caps = RE.captures(&line).expect("input should match RE pattern");
-            let ins = &caps["ins"];
-            let sign = &caps["sign"];
-            let mut amount = caps["amount"].parse::<i32>().unwrap();
-            if sign == "-" {
-                amount *= -1;
-            }
-            Ins::from(ins, amount)
-        })
-        .collect::<Vec<Ins>>()
+/// a single change made to a corrupted program
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Edit {
+    /// swap the JMP or NOP at this index
+    Swap(usize),
+    /// remove the ACC at this index from the program. The instructions after it move up by one,
+    /// so any jump over it lands one instruction further along
+    Delete(usize),
 }
 
-/// returns a Vector of indices, of NOP and JMP statements that were executed before a loop occurred
-fn ins_indices(ins: &Vec<Ins>) -> Option<Vec<usize>> {
-    let mut cidx = 0;
-    // is a list of indices into `ins` of only NOP and JMP commands
-    let mut ins_hist: Vec<usize> = vec![];
-    let mut visited = vec![false; ins.len()];
-    loop {
-        if cidx >= ins.len() {
-            // program terminated
-            return None;
-        }
-        if visited[cidx] == true {
-            // program loops
-            return Some(ins_hist);
+/// a set of edits that makes a program terminate, along with the final accumulator value
+#[derive(Debug, PartialEq, Eq)]
+struct Repair {
+    edits: Vec<Edit>,
+    acc: i64,
+}
+
+/// the kinds and number of edits a repair search may make
+#[derive(Debug, Copy, Clone)]
+struct RepairSearch {
+    /// the largest number of edits in a repair
+    max_edits: usize,
+    /// whether ACC instructions may be deleted, as well as JMP and NOP instructions swapped
+    delete_acc: bool,
+}
+
+impl Default for RepairSearch {
+    fn default() -> Self {
+        RepairSearch {
+            max_edits: 1,
+            delete_acc: false,
         }
+    }
+}
 
-        visited[cidx] = true;
-        if !Ins::is_acc(&ins[cidx]) {
-            ins_hist.push(cidx);
+/// runs the program `ins` with `edits` applied. Returns the final accumulator value if it
+/// terminates, otherwise the indices (in `ins`) of the executed instructions
+fn run_edited(ins: &[Ins], edits: &[Edit]) -> Result<i64, Vec<usize>> {
+    let mut program = vec![];
+    // original[i] is the index in `ins` of instruction i of the edited program
+    let mut original = vec![];
+    for (i, instruction) in ins.iter().enumerate() {
+        if edits.contains(&Edit::Delete(i)) {
+            continue;
         }
-        match ins[cidx] {
-            Ins::ACC(_amt) => {
-                cidx += 1;
-            }
-            Ins::JMP(amt) => {
-                cidx = (cidx as i32 + amt) as usize;
+        if edits.contains(&Edit::Swap(i)) {
+            program.push(Ins::swap(instruction));
+        } else {
+            program.push(*instruction);
+        }
+        original.push(i);
+    }
+    let mut console = Console::new(program);
+    match console.run_until_loop_or_halt() {
+        Outcome::Halted(acc) => Ok(acc),
+        _ => Err(console.executed().iter().map(|&i| original[i]).collect()),
+    }
+}
+
+impl RepairSearch {
+    /// returns every repair of the fewest edits that makes the program terminate, or an empty
+    /// Vector if no repair of up to `max_edits` edits exists. A program that already terminates
+    /// has a single repair with no edits.
+    ///
+    /// Repairs are searched breadth first by their number of edits. A swap only changes how the
+    /// program runs once it is executed, so the only swaps tried are those of JMP and NOP
+    /// instructions that ran in the program with the smaller set of edits. A deletion moves every
+    /// later instruction, so all ACC instructions are tried. The number of sets tried grows as the
+    /// size of the program to the power of `max_edits`, so it should be kept small
+    fn minimal_repairs(&self, ins: &[Ins]) -> Vec<Repair> {
+        let deletions: Vec<Edit> = if self.delete_acc {
+            (0..ins.len())
+                .filter(|&i| Ins::is_acc(&ins[i]))
+                .map(Edit::Delete)
+                .collect()
+        } else {
+            vec![]
+        };
+
+        // every edit set holds its edits in sorted order, so each set is only tried once
+        let mut edit_sets: BTreeSet<Vec<Edit>> = BTreeSet::new();
+        edit_sets.insert(vec![]);
+        for size in 0..=self.max_edits {
+            let mut repairs = vec![];
+            let mut larger_sets = BTreeSet::new();
+            for edits in &edit_sets {
+                match run_edited(ins, edits) {
+                    Ok(acc) => repairs.push(Repair {
+                        edits: edits.clone(),
+                        acc,
+                    }),
+                    Err(_) if size == self.max_edits => {}
+                    Err(executed) => {
+                        let swaps = executed
+                            .into_iter()
+                            .filter(|&i| !Ins::is_acc(&ins[i]))
+                            .map(Edit::Swap);
+                        for edit in swaps.chain(deletions.iter().copied()) {
+                            if !edits.contains(&edit) {
+                                let mut larger = edits.clone();
+                                larger.push(edit);
+                                larger.sort();
+                                larger_sets.insert(larger);
+                            }
+                        }
+                    }
+                }
             }
-            Ins::NOP(_amt) => {
-                cidx += 1;
+            if !repairs.is_empty() {
+                return repairs;
             }
+            edit_sets = larger_sets;
         }
+        vec![]
     }
 }
 
-/// if the program given by `ins` terminates, `Some(i32)` is returned containing the final
-/// accumulator value. If the program doesn't terminate, `None` is returned
-fn terminates(ins: &Vec<Ins>) -> Option<i32> {
-    let mut acc = 0;
-    let mut cidx = 0;
-    let mut visited = vec![false; ins.len()];
-    loop {
-        if cidx >= ins.len() {
-            // program terminates
-            return Some(acc);
-        }
-        if visited[cidx] == true {
-            // program has a loop
-            return None;
+fn part_two(brute_force: bool) {
+    let ins = read_program("../input/08-input.txt").unwrap_or_else(|e| panic!("{}", e));
+
+    let repaired = if brute_force {
+        brute_force_repair(&ins)
+    } else {
+        repair(&ins)
+    };
+    match repaired {
+        Some((idx, acc)) => {
+            println!("swapped instruction {} ({})", idx, &ins[idx]);
+            println!("final acc = {}", &acc);
         }
+        None => println!(
+            "no single JMP or NOP swap makes the program terminate, try: search <max edits>"
+        ),
+    }
+}
 
-        visited[cidx] = true;
-        match ins[cidx] {
-            Ins::ACC(amt) => {
-                acc += amt;
-                cidx += 1;
-            }
-            Ins::JMP(amt) => {
-                cidx = (cidx as i32 + amt) as usize;
-            }
-            Ins::NOP(_amt) => {
-                cidx += 1;
+/// searches for all the minimal repairs of up to `search.max_edits` edits
+fn search_repairs(search: RepairSearch) {
+    let ins = read_program("../input/08-input.txt").unwrap_or_else(|e| panic!("{}", e));
+
+    let repairs = search.minimal_repairs(&ins);
+    if repairs.is_empty() {
+        println!(
+            "no repair of up to {} edits makes the program terminate",
+            search.max_edits
+        );
+    }
+    for repair in repairs {
+        let edits: Vec<String> = repair
+            .edits
+            .iter()
+            .map(|edit| match *edit {
+                Edit::Swap(i) => format!("swap {} ({})", i, ins[i]),
+                Edit::Delete(i) => format!("delete {} ({})", i, ins[i]),
+            })
+            .collect();
+        println!("{} => final acc = {}", edits.join(", "), repair.acc);
+    }
+}
+
+/// usage:
+///   08-handheld-halting-p2                   repair the program by swapping one JMP or NOP
+///   08-handheld-halting-p2 brute             the same, using the (slower) brute force repair
+///   08-handheld-halting-p2 search <k> [acc]  list all the minimal repairs of up to k swaps,
+///                                            also deleting ACC instructions if `acc` is given
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        [] => part_two(false),
+        ["brute"] => part_two(true),
+        ["search", k] | ["search", k, "acc"] => match k.parse() {
+            Ok(max_edits) => search_repairs(RepairSearch {
+                max_edits,
+                delete_acc: args.len() == 3,
+            }),
+            Err(_) => {
+                eprintln!("invalid number of edits {}", k);
+                std::process::exit(1);
             }
+        },
+        _ => {
+            eprintln!(
+                "unknown arguments {:?}, expected: [brute | search <k> [acc]]",
+                args
+            );
+            std::process::exit(1);
         }
     }
 }
 
-fn part_two() {
-    let mut ins = parse_input("../input/08-input.txt");
+#[cfg(test)]
+mod tests {
+    use crate::{brute_force_repair, repair, Edit, Repair, RepairSearch};
+    use adventofcode::console::{parse_program, Ins};
 
-    if let Some(mut ins_hist) = ins_indices(&ins) {
-        let mut last_idx = *ins_hist.last().unwrap();
-        let mut last_ins = ins[last_idx];
+    const EXAMPLE: &str = "nop +0
+acc +1
+jmp +4
+acc +3
+jmp -3
+acc -99
+acc +1
+jmp -4
+acc +6";
 
-        loop {
-            if let Some(acc) = terminates(&ins) {
-                println!("final acc = {}", &acc);
-                break;
-            }
-            // else we need to: restore the last instruction that was swapped
-            ins[last_idx] = last_ins;
-
-            // swap the last JMP or NOP instruction and try again
-            if let Some(ih_idx) = ins_hist.pop() {
-                last_idx = ih_idx;
-                last_ins = ins[ih_idx];
-                ins[ih_idx] = Ins::swap(&ins[ih_idx]);
-            } else {
-                panic!("no JMP or NOP statements found to change, but we still have loops")
+    #[test]
+    fn repairs_example() {
+        let program = parse_program(EXAMPLE.lines()).unwrap();
+        assert_eq!(repair(&program), Some((7, 8)));
+        assert_eq!(brute_force_repair(&program), Some((7, 8)));
+    }
+
+    #[test]
+    fn terminating_program_needs_no_repair() {
+        assert_eq!(repair(&[Ins::ACC(1), Ins::NOP(5)]), None);
+    }
+
+    #[test]
+    fn jumps_out_of_bounds_do_not_terminate() {
+        // jmp -2 at index 1 and jmp +3 at index 2 leave the program without terminating, so the
+        // only repair is to swap the nop +3 at index 0 into a jump to the end
+        let program = vec![Ins::NOP(3), Ins::JMP(-2), Ins::JMP(3)];
+        assert_eq!(repair(&program), Some((0, 0)));
+        assert_eq!(brute_force_repair(&program), Some((0, 0)));
+    }
+
+    #[test]
+    fn repair_agrees_with_brute_force() {
+        // a chain of blocks where the loop back to the start can only be escaped by swapping
+        // the nop, in block `fix`, into a jump over the rest of the program
+        for fix in 0..20 {
+            let mut program = vec![];
+            for block in 0..20 {
+                program.push(Ins::ACC(block));
+                if block == fix {
+                    program.push(Ins::NOP(3 * (20 - block) - 1));
+                } else {
+                    program.push(Ins::NOP(1));
+                }
+                program.push(Ins::JMP(1));
             }
+            program.push(Ins::JMP(-(program.len() as i32)));
+            assert_eq!(repair(&program), brute_force_repair(&program));
         }
-    } else {
-        panic!("no JMP or NOP statements found to change")
     }
-}
 
-fn main() {
-    part_two();
+    #[test]
+    fn repairs_agree_when_several_swaps_work() {
+        // swapping either instruction terminates, both repairs pick the first one executed
+        let program = vec![Ins::NOP(2), Ins::JMP(-1)];
+        assert_eq!(repair(&program), Some((0, 0)));
+        assert_eq!(brute_force_repair(&program), Some((0, 0)));
+    }
+
+    #[test]
+    fn minimal_repairs_of_example() {
+        let program = parse_program(EXAMPLE.lines()).unwrap();
+        assert_eq!(
+            RepairSearch::default().minimal_repairs(&program),
+            vec![Repair {
+                edits: vec![Edit::Swap(7)],
+                acc: 8
+            }]
+        );
+    }
+
+    #[test]
+    fn repairs_of_two_corruptions() {
+        // neither jump can be swapped on its own without looping
+        let program = vec![Ins::JMP(0), Ins::JMP(0)];
+        assert_eq!(repair(&program), None);
+        assert_eq!(brute_force_repair(&program), None);
+        assert!(RepairSearch::default().minimal_repairs(&program).is_empty());
+
+        let search = RepairSearch {
+            max_edits: 2,
+            delete_acc: false,
+        };
+        assert_eq!(
+            search.minimal_repairs(&program),
+            vec![Repair {
+                edits: vec![Edit::Swap(0), Edit::Swap(1)],
+                acc: 0
+            }]
+        );
+    }
+
+    #[test]
+    fn repairs_by_deleting_acc() {
+        // deleting either ACC moves the self loop at index 3 out from under the first jump
+        let program = vec![Ins::JMP(3), Ins::ACC(7), Ins::ACC(1), Ins::JMP(0)];
+        assert_eq!(
+            RepairSearch::default().minimal_repairs(&program),
+            vec![Repair {
+                edits: vec![Edit::Swap(3)],
+                acc: 0
+            }]
+        );
+
+        let search = RepairSearch {
+            max_edits: 1,
+            delete_acc: true,
+        };
+        assert_eq!(
+            search.minimal_repairs(&program),
+            vec![
+                Repair {
+                    edits: vec![Edit::Swap(3)],
+                    acc: 0
+                },
+                Repair {
+                    edits: vec![Edit::Delete(1)],
+                    acc: 0
+                },
+                Repair {
+                    edits: vec![Edit::Delete(2)],
+                    acc: 0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn terminating_program_has_an_empty_repair() {
+        assert_eq!(
+            RepairSearch::default().minimal_repairs(&[Ins::ACC(1)]),
+            vec![Repair {
+                edits: vec![],
+                acc: 1
+            }]
+        );
+    }
 }
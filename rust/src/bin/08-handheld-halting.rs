@@ -1,121 +1,104 @@
 // Day 8 - Handheld Halting
 // https://adventofcode.com/2020/day/8
 
-use lazy_static::lazy_static;
-use regex::Regex;
-use std::fs::File;
+use adventofcode::cfg::Cfg;
+use adventofcode::console::{disassemble, read_program, Console, Ins, Outcome};
+use adventofcode::debugger::Debugger;
+use std::env;
 use std::io;
-use std::io::BufRead;
 
-#[derive(Debug, Copy, Clone)]
-/// All the possible Instructions
-enum Ins {
-    ACC(i32),
-    JMP(i32),
-    NOP(i32),
-}
-
-impl Ins {
-    fn from(ins: &str, amount: i32) -> Self {
-        match ins {
-            "acc" => Ins::ACC(amount),
-            "jmp" => Ins::JMP(amount),
-            _ => Ins::NOP(amount),
-        }
+// Immediately before any instruction is executed a second time, what value is in the accumulator?
+fn part_one(ins: Vec<Ins>) {
+    match Console::new(ins).run_until_loop_or_halt() {
+        Outcome::Looped(acc, _ip) => println!("program loops with accumulator = {}", &acc),
+        Outcome::Halted(acc) => println!("program terminates with acc = {}", &acc),
+        Outcome::JumpedPastEnd { acc, from, to } => println!(
+            "instruction {} jumped past the end of the program to {} with acc = {}",
+            from, to, acc
+        ),
+        Outcome::JumpedBeforeStart { acc, from, to } => println!(
+            "instruction {} jumped before the start of the program to {} with acc = {}",
+            from, to, acc
+        ),
     }
+}
 
-    /// swaps a NOP to a JMP and a JMP to a NOP. ACC returns itself
-    #[allow(dead_code)]
-    fn swap(ins: &Ins) -> Self {
-        match ins {
-            Ins::NOP(amt) => Ins::JMP(*amt),
-            Ins::JMP(amt) => Ins::NOP(*amt),
-            Ins::ACC(_amt) => *ins,
-        }
+/// prints the static analysis of the program: unreachable instructions, basic blocks, loops and
+/// the single JMP or NOP swaps that make the program terminate
+fn analyze(ins: &[Ins]) {
+    let cfg = Cfg::new(ins);
+    let unreachable = cfg.unreachable();
+    println!(
+        "{} of {} instructions are unreachable: {:?}",
+        unreachable.len(),
+        ins.len(),
+        unreachable
+    );
+    println!("{} basic blocks", cfg.basic_blocks().len());
+    for block in cfg.basic_blocks() {
+        println!(
+            "  {:>5} .. {:<5} {:?}",
+            block.start,
+            block.end,
+            cfg.successor(block.end - 1)
+        );
     }
-
-    /// returns true if `ins` is an ACC instruction
-    fn is_acc(ins: &Ins) -> bool {
-        match ins {
-            Ins::ACC(_) => true,
-            _ => false,
-        }
+    let loops = cfg.loops();
+    println!("{} loops", loops.len());
+    for l in &loops {
+        println!("  {} instructions starting at {}: {:?}", l.len(), l[0], l);
     }
-}
-
-/// parse input file into a Vector of `Ins`tructions
-fn parse_input(filename: &str) -> Vec<Ins> {
-    lazy_static! {
-        static ref RE: Regex =
-            Regex::new(r"(?P<ins>\w+) (?P<sign>[+-])(?P<amount>\d+)").expect("valid RegEx");
+    for ip in cfg.terminating_flips() {
+        println!(
+            "swapping instruction {} ({}) makes the program terminate",
+            ip, ins[ip]
+        );
     }
-    let file = File::open(filename).unwrap();
-    io::BufReader::new(file)
-        .lines()
-        .map(|l| l.unwrap())
-        .map(|line| {
-            let caps = RE.captures(&line).expect("input should match RE pattern");
-            let ins = &caps["ins"];
-            let sign = &caps["sign"];
-            let mut amount = caps["amount"].parse::<i32>().unwrap();
-            if sign == "-" {
-                amount *= -1;
-            }
-            Ins::from(ins, amount)
-        })
-        .collect::<Vec<Ins>>()
 }
 
-// if the program terminates, returns: (true, final_value_of_accumulator, Vec_of_ins_indices_run)
-// if the program does NOT terminate (i.e. it loops), returns:
-//      (false, value_of_accumulator_before_executing_loop_ins, Vec_of_ins_run)
-fn will_terminate(ins: &Vec<Ins>) -> (bool, i32, Vec<usize>) {
-    let mut acc = 0;
-    let mut cidx = 0;
-    // is a list of indices into `ins` of only NOP and JMP commands
-    let mut ins_hist: Vec<usize> = Vec::with_capacity(ins.len());
-    let mut visited = vec![false; ins.len()];
-    loop {
-        if cidx >= ins.len() {
-            // program does terminate
-            return (true, acc, ins_hist);
-        }
-        if visited[cidx] == true {
-            // program loops
-            return (false, acc, ins_hist);
+/// usage:
+///   08-handheld-halting           run the program until it loops or terminates
+///   08-handheld-halting disasm    print the program in canonical form
+///   08-handheld-halting trace     print each executed instruction with the ip and acc
+///   08-handheld-halting debug     step through the program with commands read from stdin
+///   08-handheld-halting cfg       print the control-flow analysis of the program
+///   08-handheld-halting cfg dot   print the control-flow graph in Graphviz DOT format
+fn main() {
+    let ins = read_program("../input/08-input.txt").unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        None => part_one(ins),
+        Some("disasm") => print!("{}", disassemble(&ins)),
+        Some("trace") => {
+            let stdout = io::stdout();
+            let outcome = Console::new(ins)
+                .run_traced(&mut stdout.lock())
+                .expect("trace should be written to stdout");
+            println!("{:?}", outcome);
         }
-
-        visited[cidx] = true;
-        if !Ins::is_acc(&ins[cidx]) {
-            ins_hist.push(cidx);
+        Some("debug") => {
+            let stdin = io::stdin();
+            let stdout = io::stdout();
+            Debugger::new(Console::new(ins))
+                .run(stdin.lock(), &mut stdout.lock())
+                .expect("debugger should read stdin and write stdout");
         }
-        match ins[cidx] {
-            Ins::ACC(amt) => {
-                acc += amt;
-                cidx += 1;
-            }
-            Ins::JMP(amt) => {
-                cidx = (cidx as i32 + amt) as usize;
-            }
-            Ins::NOP(_amt) => {
-                cidx += 1;
+        Some("cfg") => {
+            if args.get(1).map(String::as_str) == Some("dot") {
+                print!("{}", Cfg::new(&ins).to_dot());
+            } else {
+                analyze(&ins);
             }
         }
+        Some(other) => {
+            eprintln!(
+                "unknown command {}, expected one of: disasm, trace, debug, cfg",
+                other
+            );
+            std::process::exit(1);
+        }
     }
 }
-
-// Immediately before any instruction is executed a second time, what value is in the accumulator?
-fn part_one() {
-    //
-    let ins = parse_input("../input/08-input.txt");
-    let (term, acc, _) = will_terminate(&ins);
-    if !term {
-        println!("program loops with accumulator = {}", &acc);
-    } else {
-        println!("program terminates with acc = {}", &acc);
-    }
-}
-
-fn main() {
-    part_one();
-}
@@ -1,91 +1,417 @@
-use num_traits::PrimInt;
-use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
 /// Advent of Code - Day 9 - Encoding Error
 /// https://adventofcode.com/2020/day/9
+use std::env;
+use std::fmt;
+use std::fmt::{Display, Formatter};
 use std::fs::File;
-use std::hash::Hash;
 use std::io;
-use std::io::BufRead;
+use std::io::{BufRead, Write};
 
-/// parse input file into a Vector of integers
-fn parse_input(filename: &str) -> Vec<i64> {
-    let file = File::open(filename).unwrap();
-    io::BufReader::new(file)
+/// the errors that can occur while reading a stream of numbers
+#[derive(Debug)]
+enum StreamError {
+    Io(io::Error),
+    /// the (one based) line number and text of a line that is not an integer
+    BadNumber {
+        line: usize,
+        text: String,
+    },
+}
+
+impl Display for StreamError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamError::Io(e) => write!(f, "{}", e),
+            StreamError::BadNumber { line, text } => {
+                write!(f, "line {}: {:?} is not an integer", line, text)
+            }
+        }
+    }
+}
+
+impl From<io::Error> for StreamError {
+    fn from(e: io::Error) -> Self {
+        StreamError::Io(e)
+    }
+}
+
+/// returns an iterator over the numbers in `input`, one per line. Blank lines are skipped
+fn numbers<R: BufRead>(input: R) -> impl Iterator<Item = Result<i64, StreamError>> {
+    input
         .lines()
-        .map(|l| l.unwrap().parse::<i64>().unwrap())
-        .collect::<Vec<i64>>()
-}
-
-/// return all pairs of integers from the `v` that sum up to `sum`
-/// v - the vector of integers
-/// sum - the target sum
-fn two_sum<T>(v: &[T], sum: T) -> Vec<(T, T)>
-where
-    T: PrimInt + Hash,
-{
-    let mut imap = HashMap::new();
-    let mut res = Vec::new();
-    for i in v {
-        let target = sum - *i;
-        if imap.contains_key(&target) {
-            res.push((*i, target));
+        .enumerate()
+        .filter_map(|(i, line)| match line {
+            Err(e) => Some(Err(StreamError::from(e))),
+            Ok(line) if line.trim().is_empty() => None,
+            Ok(line) => Some(line.trim().parse().map_err(|_| StreamError::BadNumber {
+                line: i + 1,
+                text: line,
+            })),
+        })
+}
+
+/// parse input file into a Vector of integers
+fn parse_input(filename: &str) -> Result<Vec<i64>, StreamError> {
+    let file = File::open(filename)?;
+    numbers(io::BufReader::new(file)).collect()
+}
+
+/// the preamble size used by the puzzle
+const PREAMBLE: usize = 25;
+
+/// a sliding window over the last `preamble` numbers of an XMAS stream. Each number added to the
+/// window evicts the oldest one, so checking a number never has to rebuild the window
+#[derive(Debug)]
+struct XmasWindow {
+    preamble: usize,
+    window: VecDeque<i64>,
+    /// the number of times each value occurs in the window
+    counts: HashMap<i64, usize>,
+}
+
+impl XmasWindow {
+    fn new(preamble: usize) -> Self {
+        XmasWindow {
+            preamble,
+            window: VecDeque::with_capacity(preamble + 1),
+            counts: HashMap::with_capacity(preamble + 1),
         }
-        imap.entry(*i).or_insert(*i);
     }
-    res
+
+    /// returns true if the window holds a full preamble of numbers
+    fn is_full(&self) -> bool {
+        self.window.len() == self.preamble
+    }
+
+    /// returns true if `n` is the sum of two different numbers in the window. When `n - x`
+    /// overflows there is no i64 `y` with `x + y = n`, so `x` is not part of a sum
+    fn is_sum(&self, n: i64) -> bool {
+        self.window.iter().any(|&x| {
+            n.checked_sub(x)
+                .is_some_and(|y| y != x && self.counts.contains_key(&y))
+        })
+    }
+
+    /// adds `n` to the window, evicting the oldest number once the window is full.
+    /// Returns false if `n` is invalid, i.e. the window was full and `n` is not the sum of two
+    /// different numbers in it. Numbers in the preamble are always valid
+    fn push(&mut self, n: i64) -> bool {
+        let valid = !self.is_full() || self.is_sum(n);
+        self.window.push_back(n);
+        *self.counts.entry(n).or_insert(0) += 1;
+        if self.window.len() > self.preamble {
+            let oldest = self.window.pop_front().expect("window is not empty");
+            if let Entry::Occupied(mut count) = self.counts.entry(oldest) {
+                *count.get_mut() -= 1;
+                if *count.get() == 0 {
+                    count.remove();
+                }
+            }
+        }
+        valid
+    }
+}
+
+/// returns the index and value of every number in `nums` that is not the sum of two different
+/// numbers among the `preamble` numbers before it
+fn invalid_numbers(nums: &[i64], preamble: usize) -> Vec<(usize, i64)> {
+    let mut window = XmasWindow::new(preamble);
+    nums.iter()
+        .copied()
+        .enumerate()
+        .filter(|&(_, n)| !window.push(n))
+        .collect()
 }
 
-#[allow(dead_code)]
-fn valid_pair<T: PrimInt>(pair: &(T, T)) -> bool {
-    let (p1, p2) = pair;
-    *p1 != *p2
+/// finds the first number in `nums` that does not have two previous numbers (within the previous
+/// `preamble` numbers) that sum to it
+fn part_one(nums: &[i64], preamble: usize) -> Option<i64> {
+    let invalid = invalid_numbers(nums, preamble);
+    for (i, n) in &invalid {
+        println!(
+            "{} at index {} does not have two previous numbers that sum to it",
+            n, i
+        );
+    }
+    invalid.first().map(|&(_, n)| n)
 }
 
-#[allow(dead_code)]
-fn part_one(nums: Vec<i64>) -> Option<i64> {
-    for n in 25..nums.len() {
-        let sum_pairs = two_sum(&nums[(n - 25)..n], nums[n]);
-        let valid_pairs: Vec<&(i64, i64)> =
-            sum_pairs.iter().filter(|&pair| valid_pair(pair)).collect();
-        if valid_pairs.is_empty() {
-            println!(
-                "{} does not have two previous numbers that sum to it",
-                &nums[n]
-            );
-            return Some(nums[n]);
+/// validates the numbers in `input`, one per line, writing each invalid number to `out` as soon
+/// as it is read. Only the last `preamble` numbers are kept, so streams of any length can be
+/// validated. Returns the number of numbers read and the number of them that were invalid
+fn validate_stream<R: BufRead, W: Write>(
+    input: R,
+    preamble: usize,
+    out: &mut W,
+) -> Result<(usize, usize), StreamError> {
+    let mut window = XmasWindow::new(preamble);
+    let mut read = 0;
+    let mut invalid = 0;
+    for n in numbers(input) {
+        let n = n?;
+        if !window.push(n) {
+            writeln!(out, "{} at index {} is invalid", n, read)?;
+            out.flush()?;
+            invalid += 1;
         }
+        read += 1;
     }
-    None
+    Ok((read, invalid))
 }
 
 // part 2 functions start here
 
-/// returns a vector of sorted integers, that are a contiguous slice from `nums` that
-/// sum up to `target`
-/// This is gonna use a brute force approach
-fn part_two(nums: Vec<i64>, target: i64) -> Option<Vec<i64>> {
-    for window_size in 2..nums.len() {
-        for window in nums.windows(window_size) {
-            let mut slice = window.to_owned();
-            slice.sort_unstable();
-            // don't bother summing a slice that contains an element >= target
-            if *slice.last().unwrap() < target {
-                if slice.iter().sum::<i64>() == target {
-                    let enc_weak = slice.first().unwrap() + slice.last().unwrap();
-                    println!(
-                        "encryption weakness = {} from elements {:?}",
-                        &enc_weak, &slice
-                    );
-                    return Some(slice);
-                }
-            }
+/// a contiguous range of numbers that sums to the invalid number, along with the encryption
+/// weakness: the sum of the smallest and largest numbers in the range
+#[derive(Debug, PartialEq, Eq)]
+struct Weakness {
+    /// index of the first number in the range
+    start: usize,
+    /// index of the last number in the range
+    end: usize,
+    weakness: i64,
+}
+
+/// finds a contiguous range, of at least two numbers from `nums`, that sums to `target`.
+/// The range that ends first is returned, and the longest one if several ranges end there.
+///
+/// The sum of `nums[i..=j]` is `prefix[j + 1] - prefix[i]`, where `prefix[k]` is the sum of the
+/// first `k` numbers. Walking `j` forwards while remembering the first index of each prefix sum
+/// finds the range in linear time. Unlike a two pointer search, this works when `nums` holds
+/// negative numbers. The prefix sums are kept as i128, so they cannot overflow however many
+/// numbers there are
+fn contiguous_sum(nums: &[i64], target: i64) -> Option<Weakness> {
+    // maps each prefix sum to the lowest `i` that has that sum. Only prefixes that leave at least
+    // two numbers in the range are added
+    let mut first_index: HashMap<i128, usize> = HashMap::new();
+    let mut prefix = vec![0i128; nums.len() + 1];
+    for (j, n) in nums.iter().enumerate() {
+        prefix[j + 1] = prefix[j] + i128::from(*n);
+        if j >= 1 {
+            first_index.entry(prefix[j - 1]).or_insert(j - 1);
+        }
+        if let Some(&start) = first_index.get(&(prefix[j + 1] - i128::from(target))) {
+            let range = &nums[start..=j];
+            let min = range.iter().min().expect("range is not empty");
+            let max = range.iter().max().expect("range is not empty");
+            return Some(Weakness {
+                start,
+                end: j,
+                weakness: min + max,
+            });
         }
     }
     None
 }
 
+/// finds the contiguous range of numbers in `nums` that sums to `target`, and prints the
+/// encryption weakness
+fn part_two(nums: &[i64], target: i64) -> Option<Weakness> {
+    let found = contiguous_sum(nums, target);
+    match &found {
+        Some(Weakness {
+            start,
+            end,
+            weakness,
+        }) => println!(
+            "encryption weakness = {} from elements {}..={} {:?}",
+            weakness,
+            start,
+            end,
+            &nums[*start..=*end]
+        ),
+        None => println!("no contiguous range of numbers sums to {}", target),
+    }
+    found
+}
+
+/// validates the numbers in `file`, or in stdin if no file is given, printing the invalid
+/// numbers as they are read
+fn stream(preamble: usize, file: Option<&str>) -> Result<(), StreamError> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let (read, invalid) = match file {
+        Some(file) => validate_stream(io::BufReader::new(File::open(file)?), preamble, &mut out)?,
+        None => validate_stream(io::stdin().lock(), preamble, &mut out)?,
+    };
+    writeln!(out, "read {} numbers, {} invalid", read, invalid)?;
+    Ok(())
+}
+
+/// usage:
+///   09-encoding-error [preamble]                find the first invalid number and the encryption
+///                                               weakness of the puzzle input
+///   09-encoding-error stream <preamble> [file]  print the invalid numbers in `file`, or in
+///                                               stdin, as soon as they are read
+/// the preamble size defaults to 25
 fn main() {
-    let nums = parse_input("../input/09-input.txt");
-    //part_one(nums);
-    part_two(nums, 41682220);
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let parse_preamble = |arg: &str| -> Result<usize, String> {
+        arg.parse()
+            .ok()
+            .filter(|&preamble| preamble > 0)
+            .ok_or_else(|| format!("preamble should be a positive integer, found {:?}", arg))
+    };
+    let usage = "expected: [preamble] | stream <preamble> [file]";
+
+    let result = match args.as_slice() {
+        ["stream"] => Err(format!("stream needs a preamble, {}", usage)),
+        ["stream", preamble] => parse_preamble(preamble)
+            .and_then(|preamble| stream(preamble, None).map_err(|e| e.to_string())),
+        ["stream", preamble, file] => parse_preamble(preamble)
+            .and_then(|preamble| stream(preamble, Some(file)).map_err(|e| e.to_string())),
+        [] | [_] => args
+            .first()
+            .map_or(Ok(PREAMBLE), |arg| parse_preamble(arg))
+            .and_then(|preamble| {
+                let nums = parse_input("../input/09-input.txt").map_err(|e| e.to_string())?;
+                if let Some(target) = part_one(&nums, preamble) {
+                    part_two(&nums, target);
+                }
+                Ok(())
+            }),
+        _ => Err(format!("unknown arguments {:?}, {}", args, usage)),
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        contiguous_sum, invalid_numbers, numbers, validate_stream, StreamError, Weakness,
+        XmasWindow,
+    };
+
+    const EXAMPLE: [i64; 20] = [
+        35, 20, 15, 25, 47, 40, 62, 55, 65, 95, 102, 117, 150, 182, 127, 219, 299, 277, 309, 576,
+    ];
+
+    #[test]
+    fn finds_invalid_numbers_of_example() {
+        assert_eq!(invalid_numbers(&EXAMPLE, 5), vec![(14, 127)]);
+    }
+
+    #[test]
+    fn sums_must_use_two_different_numbers() {
+        let mut window = XmasWindow::new(2);
+        assert!(window.push(3));
+        assert!(window.push(3));
+        assert!(!window.push(6));
+        // the window is now [3, 6]
+        assert!(window.push(9));
+        assert!(!window.push(12));
+    }
+
+    #[test]
+    fn sums_near_the_limits_do_not_overflow() {
+        let mut window = XmasWindow::new(2);
+        assert!(window.push(-1));
+        assert!(window.push(i64::MAX));
+        assert!(!window.push(i64::MAX));
+    }
+
+    #[test]
+    fn reports_every_invalid_number() {
+        let nums = [1, 2, 3, 100, 5, 200, 205];
+        assert_eq!(invalid_numbers(&nums, 2), vec![(3, 100), (4, 5), (5, 200)]);
+    }
+
+    #[test]
+    fn finds_weakness_of_example() {
+        assert_eq!(
+            contiguous_sum(&EXAMPLE, 127),
+            Some(Weakness {
+                start: 2,
+                end: 5,
+                weakness: 62
+            })
+        );
+    }
+
+    #[test]
+    fn range_has_at_least_two_numbers() {
+        assert_eq!(contiguous_sum(&[1, 7, 2], 7), None);
+        assert_eq!(
+            contiguous_sum(&[1, 7, 0], 7),
+            Some(Weakness {
+                start: 1,
+                end: 2,
+                weakness: 7
+            })
+        );
+    }
+
+    #[test]
+    fn handles_negative_numbers() {
+        // a two pointer search would give up on -3 + 10 after 5 + -3 + 10 overshoots
+        assert_eq!(
+            contiguous_sum(&[5, -3, 10, 1], 7),
+            Some(Weakness {
+                start: 1,
+                end: 2,
+                weakness: 7
+            })
+        );
+        assert_eq!(
+            contiguous_sum(&[4, -6, -2, 9], -8),
+            Some(Weakness {
+                start: 1,
+                end: 2,
+                weakness: -8
+            })
+        );
+    }
+
+    #[test]
+    fn prefix_sums_do_not_overflow() {
+        // the prefix sum passes i64::MAX before the range starts
+        let nums = [i64::MAX, i64::MAX, 1, 2];
+        assert_eq!(
+            contiguous_sum(&nums, 3),
+            Some(Weakness {
+                start: 2,
+                end: 3,
+                weakness: 3
+            })
+        );
+        assert_eq!(
+            contiguous_sum(&[i64::MAX, 1, -2], i64::MAX - 1),
+            Some(Weakness {
+                start: 0,
+                end: 2,
+                weakness: i64::MAX - 2
+            })
+        );
+    }
+
+    #[test]
+    fn streams_invalid_numbers_of_example() {
+        let input: String = EXAMPLE.iter().map(|n| format!("{}\n", n)).collect();
+        let mut out = vec![];
+        assert_eq!(
+            validate_stream(input.as_bytes(), 5, &mut out).unwrap(),
+            (20, 1)
+        );
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "127 at index 14 is invalid\n"
+        );
+    }
+
+    #[test]
+    fn reports_line_of_bad_number() {
+        let input = "1\n2\n\n3x\n";
+        match numbers(input.as_bytes()).collect::<Result<Vec<i64>, _>>() {
+            Err(StreamError::BadNumber { line, text }) => {
+                assert_eq!((line, text.as_str()), (4, "3x"));
+            }
+            other => panic!("expected a bad number, got {:?}", other),
+        }
+    }
 }
@@ -0,0 +1,385 @@
+// Day 10 - Adapter Array
+// https://adventofcode.com/2020/day/10
+
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io;
+use std::io::BufRead;
+
+/// the maximum amount that the joltage can differ from one adapter to the next, unless another
+/// gap is given on the command line
+const TOLERANCE: u32 = 3;
+
+/// the adapters can not all be chained together, as the joltage jumps from `from` to `to`,
+/// which is more than `max_gap`
+#[derive(Debug, PartialEq, Eq)]
+struct GapTooLarge {
+    from: u32,
+    to: u32,
+    max_gap: u32,
+}
+
+impl Display for GapTooLarge {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "adapter {} can not be connected to {}, the difference is greater than {}",
+            self.to, self.from, self.max_gap
+        )
+    }
+}
+
+/// parse input file into a Vector of adapter joltages
+fn parse_input(filename: &str) -> io::Result<Vec<u32>> {
+    let file = File::open(filename)?;
+    io::BufReader::new(file)
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.as_ref().map_or(true, |l| !l.trim().is_empty()))
+        .map(|(i, line)| {
+            let line = line?;
+            line.trim().parse().map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: {:?} is not a joltage", i + 1, line),
+                )
+            })
+        })
+        .collect()
+}
+
+/// returns the joltages of the whole chain in ascending order: the charging outlet (0), every
+/// adapter, and the device's built-in adapter, which is always `max_gap` higher than the highest
+/// adapter. Panics if the device's joltage does not fit in a u32
+fn chain(adapters: &[u32], max_gap: u32) -> Vec<u32> {
+    let mut chain = Vec::with_capacity(adapters.len() + 2);
+    chain.push(0);
+    chain.extend_from_slice(adapters);
+    chain.sort_unstable();
+    let device = chain
+        .last()
+        .copied()
+        .unwrap_or(0)
+        .checked_add(max_gap)
+        .expect("device joltage should fit in a u32");
+    chain.push(device);
+    chain
+}
+
+/// computes the joltage differences when every adapter is used, from the charging outlet to the
+/// device. Returns a map from each difference to the joltages that differ by that amount from
+/// the joltage before them
+fn joltage_differences(
+    adapters: &[u32],
+    max_gap: u32,
+) -> Result<BTreeMap<u32, Vec<u32>>, GapTooLarge> {
+    let mut diffs: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+    for pair in chain(adapters, max_gap).windows(2) {
+        let (from, to) = (pair[0], pair[1]);
+        if to - from > max_gap {
+            return Err(GapTooLarge { from, to, max_gap });
+        }
+        diffs.entry(to - from).or_default().push(to);
+    }
+    Ok(diffs)
+}
+
+/// returns the number of distinct ways the adapters can be arranged to connect the charging
+/// outlet to the device. `counts[i]` holds the number of ways to get from the i-th joltage of
+/// the chain to the device, which is the sum of the counts of the joltages within `max_gap`
+/// above it. The count grows exponentially with the number of adapters, so it is a `BigUint`
+fn arrangements(adapters: &[u32], max_gap: u32) -> BigUint {
+    let chain = chain(adapters, max_gap);
+    let mut counts = vec![BigUint::zero(); chain.len()];
+    counts[chain.len() - 1] = BigUint::one();
+    for i in (0..chain.len() - 1).rev() {
+        let mut count = BigUint::zero();
+        for j in (i + 1..chain.len()).take_while(|&j| chain[j] - chain[i] <= max_gap) {
+            count += &counts[j];
+        }
+        counts[i] = count;
+    }
+    counts.swap_remove(0)
+}
+
+/// returns the chain, from the charging outlet to the device, that uses the fewest adapters.
+/// Each step greedily connects the highest joltage adapter within `max_gap`, as any chain
+/// that connects a lower one can never get further with fewer adapters
+fn fewest_adapters(adapters: &[u32], max_gap: u32) -> Result<Vec<u32>, GapTooLarge> {
+    let chain = chain(adapters, max_gap);
+    let mut fewest = vec![chain[0]];
+    let mut i = 0;
+    while i < chain.len() - 1 {
+        let next = (i + 1..chain.len())
+            .take_while(|&j| chain[j] - chain[i] <= max_gap)
+            .last()
+            .ok_or(GapTooLarge {
+                from: chain[i],
+                to: chain[i + 1],
+                max_gap,
+            })?;
+        fewest.push(chain[next]);
+        i = next;
+    }
+    Ok(fewest)
+}
+
+/// a lazy iterator over every valid arrangement of the adapters, each given as the joltages of
+/// the chain from the charging outlet to the device. Arrangements are produced in lexicographic
+/// order of the adapters they use, so the first uses every adapter
+#[derive(Debug)]
+struct Chains {
+    chain: Vec<u32>,
+    max_gap: u32,
+    /// the indices, into `chain`, of the arrangement being built. Empty once all arrangements
+    /// have been produced
+    path: Vec<usize>,
+}
+
+impl Chains {
+    fn new(adapters: &[u32], max_gap: u32) -> Self {
+        let chain = chain(adapters, max_gap);
+        // with a gap too large anywhere there are no arrangements, and the search would try
+        // every subset of the adapters before the gap to find that out. Otherwise every partial
+        // arrangement can be completed, so the search never has to backtrack far
+        let path = if chain.windows(2).all(|w| w[1] - w[0] <= max_gap) {
+            vec![0]
+        } else {
+            vec![]
+        };
+        Chains {
+            chain,
+            max_gap,
+            path,
+        }
+    }
+
+    /// returns true if the adapter at index `to` can be connected to the one at index `from`
+    fn fits(&self, from: usize, to: usize) -> bool {
+        to < self.chain.len() && self.chain[to] - self.chain[from] <= self.max_gap
+    }
+
+    /// replaces the last adapter of the path with the next one that fits its predecessor,
+    /// removing adapters that have no next one
+    fn backtrack(&mut self) {
+        while let Some(last) = self.path.pop() {
+            if let Some(&prev) = self.path.last() {
+                if self.fits(prev, last + 1) {
+                    self.path.push(last + 1);
+                    return;
+                }
+            }
+        }
+    }
+}
+
+impl Iterator for Chains {
+    type Item = Vec<u32>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(&last) = self.path.last() {
+            if last == self.chain.len() - 1 {
+                let found = self.path.iter().map(|&i| self.chain[i]).collect();
+                self.backtrack();
+                return Some(found);
+            }
+            // the chain is sorted, so if the next adapter doesn't fit then no later one can
+            if self.fits(last, last + 1) {
+                self.path.push(last + 1);
+            } else {
+                self.backtrack();
+            }
+        }
+        None
+    }
+}
+
+fn part_one(adapters: &[u32], max_gap: u32) -> Result<(), GapTooLarge> {
+    let diffs = joltage_differences(adapters, max_gap)?;
+    let count = |diff: u32| diffs.get(&diff).map_or(0, Vec::len);
+    println!("difference product = {}", count(1) * count(3));
+    for (diff, joltages) in &diffs {
+        println!("{}: {} {:?}", diff, joltages.len(), joltages);
+    }
+    Ok(())
+}
+
+fn part_two(adapters: &[u32], max_gap: u32) {
+    println!(
+        "total number of distinct ways to arrange adapters: {}",
+        arrangements(adapters, max_gap)
+    );
+}
+
+/// usage:
+///   10-adapter-array [max gap]             print the joltage differences and the number of
+///                                          arrangements
+///   10-adapter-array chains <n> [max gap]  print the first n arrangements
+///   10-adapter-array fewest [max gap]      print the arrangement that uses the fewest adapters
+/// the maximum joltage gap between adapters defaults to 3
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let adapters = parse_input("../input/10-input.txt").unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let highest = adapters.iter().copied().max().unwrap_or(0);
+    let parse_gap = |gap: Option<&&str>| -> Result<u32, String> {
+        let max_gap =
+            match gap {
+                Some(gap) => gap.parse().ok().filter(|&gap| gap > 0).ok_or_else(|| {
+                    format!("max gap should be a positive integer, found {:?}", gap)
+                })?,
+                None => TOLERANCE,
+            };
+        match highest.checked_add(max_gap) {
+            Some(_) => Ok(max_gap),
+            None => Err(format!(
+                "max gap {} is too large, the device joltage must fit in a u32",
+                max_gap
+            )),
+        }
+    };
+
+    let result = match args.as_slice() {
+        ["chains", n, gap @ ..] if gap.len() <= 1 => n
+            .parse()
+            .map_err(|_| format!("number of chains should be an integer, found {:?}", n))
+            .and_then(|n| {
+                for chain in Chains::new(&adapters, parse_gap(gap.first())?).take(n) {
+                    println!("{:?}", chain);
+                }
+                Ok(())
+            }),
+        ["fewest", gap @ ..] if gap.len() <= 1 => parse_gap(gap.first()).and_then(|max_gap| {
+            fewest_adapters(&adapters, max_gap)
+                .map(|chain| println!("{} adapters: {:?}", chain.len() - 2, chain))
+                .map_err(|e| e.to_string())
+        }),
+        [] | [_] => parse_gap(args.first()).and_then(|max_gap| {
+            part_one(&adapters, max_gap)
+                .map(|_| part_two(&adapters, max_gap))
+                .map_err(|e| e.to_string())
+        }),
+        _ => Err(format!(
+            "unknown arguments {:?}, expected: [max gap] | chains <n> [max gap] | fewest [max gap]",
+            args
+        )),
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        arrangements, fewest_adapters, joltage_differences, Chains, GapTooLarge, TOLERANCE,
+    };
+    use num_bigint::BigUint;
+
+    const SMALL: [u32; 11] = [16, 10, 15, 5, 1, 11, 7, 19, 6, 12, 4];
+
+    const LARGE: [u32; 31] = [
+        28, 33, 18, 42, 31, 14, 46, 20, 48, 47, 24, 23, 49, 45, 19, 38, 39, 11, 1, 32, 25, 35, 8,
+        17, 7, 9, 4, 2, 34, 10, 3,
+    ];
+
+    #[test]
+    fn joltage_differences_of_examples() {
+        let diffs = joltage_differences(&SMALL, TOLERANCE).unwrap();
+        assert_eq!(diffs[&1].len(), 7);
+        assert_eq!(diffs[&3], vec![4, 10, 15, 19, 22]);
+        assert!(!diffs.contains_key(&2));
+
+        let diffs = joltage_differences(&LARGE, TOLERANCE).unwrap();
+        assert_eq!((diffs[&1].len(), diffs[&3].len()), (22, 10));
+    }
+
+    #[test]
+    fn gap_too_large() {
+        assert_eq!(
+            joltage_differences(&[1, 2, 6], TOLERANCE),
+            Err(GapTooLarge {
+                from: 2,
+                to: 6,
+                max_gap: 3
+            })
+        );
+        assert_eq!(arrangements(&[1, 2, 6], TOLERANCE), BigUint::from(0u32));
+        assert_eq!(Chains::new(&[1, 2, 6], TOLERANCE).next(), None);
+        assert_eq!(
+            fewest_adapters(&[1, 2, 6], TOLERANCE),
+            Err(GapTooLarge {
+                from: 2,
+                to: 6,
+                max_gap: 3
+            })
+        );
+    }
+
+    #[test]
+    fn arrangements_of_examples() {
+        assert_eq!(arrangements(&SMALL, TOLERANCE), BigUint::from(8u32));
+        assert_eq!(arrangements(&LARGE, TOLERANCE), BigUint::from(19208u32));
+        assert_eq!(arrangements(&[], TOLERANCE), BigUint::from(1u32));
+    }
+
+    #[test]
+    fn arrangements_overflow_u64() {
+        // every adapter from 1 to 120 gives a tribonacci number of arrangements, beyond u64::MAX
+        let adapters: Vec<u32> = (1..=120).collect();
+        assert!(arrangements(&adapters, TOLERANCE) > BigUint::from(u64::MAX));
+    }
+
+    #[test]
+    fn enumerates_every_chain() {
+        let chains: Vec<Vec<u32>> = Chains::new(&SMALL, TOLERANCE).collect();
+        assert_eq!(chains.len(), 8);
+        assert_eq!(
+            chains[0],
+            vec![0, 1, 4, 5, 6, 7, 10, 11, 12, 15, 16, 19, 22]
+        );
+        assert_eq!(chains[7], vec![0, 1, 4, 7, 10, 12, 15, 16, 19, 22]);
+        assert_eq!(Chains::new(&LARGE, TOLERANCE).count(), 19208);
+    }
+
+    #[test]
+    fn no_chains_across_a_gap_too_large() {
+        // a dense run of adapters has billions of partial arrangements to try before the gap
+        let adapters: Vec<u32> = (1..=40).chain(std::iter::once(100)).collect();
+        assert_eq!(Chains::new(&adapters, TOLERANCE).next(), None);
+    }
+
+    #[test]
+    fn finds_chain_with_fewest_adapters() {
+        assert_eq!(
+            fewest_adapters(&SMALL, TOLERANCE),
+            Ok(vec![0, 1, 4, 7, 10, 12, 15, 16, 19, 22])
+        );
+        assert_eq!(fewest_adapters(&[], TOLERANCE), Ok(vec![0, 3]));
+    }
+
+    #[test]
+    fn larger_gaps_allow_more_chains() {
+        // with a gap of 1, every adapter must be used
+        let adapters = [1, 2, 3, 4];
+        assert_eq!(arrangements(&adapters, 1), BigUint::from(1u32));
+        assert_eq!(Chains::new(&adapters, 1).count(), 1);
+        assert_eq!(fewest_adapters(&adapters, 1), Ok(vec![0, 1, 2, 3, 4, 5]));
+
+        // with a gap of 4, the device at 8 can be reached from 4, and 4 from the outlet
+        assert_eq!(arrangements(&adapters, 4), BigUint::from(8u32));
+        assert_eq!(Chains::new(&adapters, 4).count(), 8);
+        assert_eq!(fewest_adapters(&adapters, 4), Ok(vec![0, 4, 8]));
+        assert!(joltage_differences(&[1, 5], 3).is_err());
+        assert_eq!(joltage_differences(&[1, 5], 4).unwrap()[&4], vec![5, 9]);
+    }
+}
@@ -0,0 +1,357 @@
+//! Static analysis of day 8 programs through their control-flow graph.
+//!
+//! Every instruction has exactly one successor, so the graph never branches. Loops are cycles of
+//! instructions, and the instructions that terminate are found by walking the edges backwards
+//! from the end of the program.
+
+use crate::console::{Ins, Instruction, Registers};
+
+/// where control moves to after an instruction has executed
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Successor {
+    /// another instruction of the program
+    Ins(usize),
+    /// the instruction immediately after the last one, i.e. the program terminates
+    End,
+    /// an index outside of the program that is not the end
+    OutOfBounds(isize),
+}
+
+/// a run of instructions that is only entered at its first instruction and only left after its
+/// last instruction
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    /// index of the first instruction in the block
+    pub start: usize,
+    /// index one past the last instruction in the block
+    pub end: usize,
+}
+
+/// returns the index of the instruction executed after `ins`, when `ins` is at index `ip`.
+/// The returned index may be outside of the program
+pub fn next_ip(ins: &Ins, ip: usize) -> isize {
+    let mut regs = Registers {
+        acc: 0,
+        ip: ip as isize,
+    };
+    ins.execute(&mut regs);
+    regs.ip
+}
+
+/// the control-flow graph of a program
+#[derive(Debug, Clone)]
+pub struct Cfg<'a> {
+    program: &'a [Ins],
+    successors: Vec<Successor>,
+    // preds[j] holds the instructions that continue to j, with preds[len] holding the
+    // instructions that terminate the program
+    preds: Vec<Vec<usize>>,
+}
+
+impl<'a> Cfg<'a> {
+    pub fn new(program: &'a [Ins]) -> Self {
+        let successors: Vec<Successor> = program
+            .iter()
+            .enumerate()
+            .map(|(i, ins)| successor(program.len(), next_ip(ins, i)))
+            .collect();
+        let mut preds = vec![vec![]; program.len() + 1];
+        for (i, succ) in successors.iter().enumerate() {
+            match succ {
+                Successor::Ins(j) => preds[*j].push(i),
+                Successor::End => preds[program.len()].push(i),
+                Successor::OutOfBounds(_) => {}
+            }
+        }
+        Cfg {
+            program,
+            successors,
+            preds,
+        }
+    }
+
+    pub fn successor(&self, i: usize) -> Successor {
+        self.successors[i]
+    }
+
+    /// returns the indices of the instructions that run, in execution order, when the program is
+    /// started at its first instruction. The path stops before an instruction would repeat
+    pub fn path(&self) -> Vec<usize> {
+        let mut seen = vec![false; self.program.len()];
+        let mut path = vec![];
+        let mut next = if self.program.is_empty() {
+            Successor::End
+        } else {
+            Successor::Ins(0)
+        };
+        while let Successor::Ins(i) = next {
+            if seen[i] {
+                break;
+            }
+            seen[i] = true;
+            path.push(i);
+            next = self.successors[i];
+        }
+        path
+    }
+
+    /// returns the indices of the instructions that can never run when the program is started
+    /// at its first instruction, in ascending order
+    pub fn unreachable(&self) -> Vec<usize> {
+        let mut reachable = vec![false; self.program.len()];
+        for i in self.path() {
+            reachable[i] = true;
+        }
+        (0..self.program.len()).filter(|&i| !reachable[i]).collect()
+    }
+
+    /// splits the program into basic blocks, in program order. A block starts at the first
+    /// instruction, at the target of a jump, and after a jump
+    pub fn basic_blocks(&self) -> Vec<BasicBlock> {
+        let len = self.program.len();
+        let mut leader = vec![false; len];
+        if len > 0 {
+            leader[0] = true;
+        }
+        for (i, ins) in self.program.iter().enumerate() {
+            if let Ins::JMP(_) = ins {
+                if i + 1 < len {
+                    leader[i + 1] = true;
+                }
+                if let Successor::Ins(target) = self.successors[i] {
+                    leader[target] = true;
+                }
+            }
+        }
+        let starts: Vec<usize> = (0..len).filter(|&i| leader[i]).collect();
+        starts
+            .iter()
+            .enumerate()
+            .map(|(b, &start)| BasicBlock {
+                start,
+                end: starts.get(b + 1).copied().unwrap_or(len),
+            })
+            .collect()
+    }
+
+    /// returns every loop in the program, whether or not it is reachable. Each loop holds its
+    /// instructions in execution order starting at its lowest index, and the loops are sorted by
+    /// that index
+    pub fn loops(&self) -> Vec<Vec<usize>> {
+        const UNVISITED: u8 = 0;
+        const ON_PATH: u8 = 1;
+        const DONE: u8 = 2;
+
+        let mut state = vec![UNVISITED; self.program.len()];
+        let mut loops = vec![];
+        for start in 0..self.program.len() {
+            // follow the only successor from `start` until reaching an instruction seen before,
+            // or leaving the program
+            let mut path = vec![];
+            let mut next = Successor::Ins(start);
+            while let Successor::Ins(i) = next {
+                match state[i] {
+                    UNVISITED => {
+                        state[i] = ON_PATH;
+                        path.push(i);
+                        next = self.successors[i];
+                    }
+                    ON_PATH => {
+                        // the path ran into itself, everything from `i` onwards is a new loop
+                        let from = path.iter().position(|&j| j == i).unwrap();
+                        let mut cycle = path[from..].to_vec();
+                        let lowest = (0..cycle.len()).min_by_key(|&k| cycle[k]).unwrap();
+                        cycle.rotate_left(lowest);
+                        loops.push(cycle);
+                        break;
+                    }
+                    _ => break,
+                }
+            }
+            for i in path {
+                state[i] = DONE;
+            }
+        }
+        loops.sort();
+        loops
+    }
+
+    /// returns a Vector where element `i` is true if running the program, starting from instruction
+    /// `i`, terminates (i.e. moves to the instruction immediately after the last one). It is
+    /// computed by walking the edges backwards from the end of the program, so each instruction
+    /// is visited at most once
+    pub fn terminating(&self) -> Vec<bool> {
+        let mut terminates = vec![false; self.program.len()];
+        let mut to_visit = self.preds[self.program.len()].clone();
+        while let Some(i) = to_visit.pop() {
+            if !terminates[i] {
+                terminates[i] = true;
+                to_visit.extend(&self.preds[i]);
+            }
+        }
+        terminates
+    }
+
+    /// returns the indices, in execution order, of every JMP or NOP whose swap on its own makes
+    /// the program terminate. A program that already terminates needs no swap, so the result is
+    /// empty.
+    ///
+    /// Only instructions on the path from the first instruction are candidates, as swapping
+    /// any other instruction does not change how the program runs. Once swapped, the instruction
+    /// cannot be reached again from its new successor (or it would already terminate), so the
+    /// swap works exactly when the new successor terminates in the unmodified program
+    pub fn terminating_flips(&self) -> Vec<usize> {
+        let terminates = self.terminating();
+        if terminates.first().copied().unwrap_or(true) {
+            return vec![];
+        }
+        self.path()
+            .into_iter()
+            .filter(|&i| !Ins::is_acc(&self.program[i]))
+            .filter(|&i| {
+                match successor(self.program.len(), next_ip(&Ins::swap(&self.program[i]), i)) {
+                    Successor::Ins(j) => terminates[j],
+                    Successor::End => true,
+                    Successor::OutOfBounds(_) => false,
+                }
+            })
+            .collect()
+    }
+
+    /// returns the graph of basic blocks in the DOT language of Graphviz. Blocks that can never
+    /// run are drawn dashed
+    pub fn to_dot(&self) -> String {
+        let blocks = self.basic_blocks();
+        let unreachable = self.unreachable();
+
+        let mut dot = String::from("digraph cfg {\n");
+        dot.push_str("    node [shape=box, fontname=\"monospace\"];\n");
+        for block in &blocks {
+            let label: String = (block.start..block.end)
+                .map(|i| format!("{}: {}\\l", i, self.program[i]))
+                .collect();
+            let style = if unreachable.binary_search(&block.start).is_ok() {
+                ", style=dashed"
+            } else {
+                ""
+            };
+            dot.push_str(&format!(
+                "    b{} [label=\"{}\"{}];\n",
+                block.start, label, style
+            ));
+        }
+        dot.push_str("    end [shape=doublecircle];\n");
+        for block in &blocks {
+            let target = match self.successors[block.end - 1] {
+                Successor::Ins(j) => format!("b{}", j),
+                Successor::End => String::from("end"),
+                Successor::OutOfBounds(to) => {
+                    let node = format!("\"out of bounds {}\"", to);
+                    dot.push_str(&format!("    {} [shape=octagon];\n", node));
+                    node
+                }
+            };
+            dot.push_str(&format!("    b{} -> {};\n", block.start, target));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// classifies the instruction pointer `ip` of a program with `len` instructions
+fn successor(len: usize, ip: isize) -> Successor {
+    if ip >= 0 && (ip as usize) < len {
+        Successor::Ins(ip as usize)
+    } else if ip == len as isize {
+        Successor::End
+    } else {
+        Successor::OutOfBounds(ip)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BasicBlock, Cfg, Successor};
+    use crate::console::{parse_program, Ins, EXAMPLE};
+
+    #[test]
+    fn successors_classify_jumps() {
+        let program = vec![Ins::JMP(2), Ins::JMP(-2), Ins::NOP(0)];
+        let cfg = Cfg::new(&program);
+        assert_eq!(cfg.successor(0), Successor::Ins(2));
+        assert_eq!(cfg.successor(1), Successor::OutOfBounds(-1));
+        assert_eq!(cfg.successor(2), Successor::End);
+    }
+
+    #[test]
+    fn path_and_unreachable_of_example() {
+        let program = parse_program(EXAMPLE.lines()).unwrap();
+        let cfg = Cfg::new(&program);
+        assert_eq!(cfg.path(), vec![0, 1, 2, 6, 7, 3, 4]);
+        assert_eq!(cfg.unreachable(), vec![5, 8]);
+    }
+
+    #[test]
+    fn basic_blocks_of_example() {
+        let program = parse_program(EXAMPLE.lines()).unwrap();
+        let blocks: Vec<(usize, usize)> = Cfg::new(&program)
+            .basic_blocks()
+            .iter()
+            .map(|&BasicBlock { start, end }| (start, end))
+            .collect();
+        assert_eq!(blocks, vec![(0, 1), (1, 3), (3, 5), (5, 6), (6, 8), (8, 9)]);
+    }
+
+    #[test]
+    fn loops_of_example() {
+        let program = parse_program(EXAMPLE.lines()).unwrap();
+        assert_eq!(Cfg::new(&program).loops(), vec![vec![1, 2, 6, 7, 3, 4]]);
+
+        // one unreachable self loop, and a loop entered part way through
+        let program = vec![Ins::JMP(2), Ins::JMP(0), Ins::NOP(0), Ins::JMP(-1)];
+        assert_eq!(Cfg::new(&program).loops(), vec![vec![1], vec![2, 3]]);
+    }
+
+    #[test]
+    fn terminating_of_example() {
+        let program = parse_program(EXAMPLE.lines()).unwrap();
+        assert_eq!(
+            Cfg::new(&program).terminating(),
+            vec![false, false, false, false, false, false, false, false, true]
+        );
+    }
+
+    #[test]
+    fn terminating_flips_of_example() {
+        let program = parse_program(EXAMPLE.lines()).unwrap();
+        assert_eq!(Cfg::new(&program).terminating_flips(), vec![7]);
+
+        // swapping the self jump runs on to the end, and a terminating program needs no swaps
+        let program = vec![Ins::JMP(0), Ins::ACC(1)];
+        assert_eq!(Cfg::new(&program).terminating_flips(), vec![0]);
+        let program = vec![Ins::NOP(0), Ins::JMP(-1)];
+        assert_eq!(Cfg::new(&program).terminating_flips(), vec![1]);
+        assert!(Cfg::new(&[Ins::ACC(1)]).terminating_flips().is_empty());
+    }
+
+    #[test]
+    fn dot_of_example() {
+        let program = vec![Ins::NOP(0), Ins::JMP(2), Ins::ACC(1), Ins::JMP(-5)];
+        let dot = Cfg::new(&program).to_dot();
+        assert_eq!(
+            dot,
+            "digraph cfg {
+    node [shape=box, fontname=\"monospace\"];
+    b0 [label=\"0: nop +0\\l1: jmp +2\\l\"];
+    b2 [label=\"2: acc +1\\l\", style=dashed];
+    b3 [label=\"3: jmp -5\\l\"];
+    end [shape=doublecircle];
+    b0 -> b3;
+    b2 -> b3;
+    \"out of bounds -2\" [shape=octagon];
+    b3 -> \"out of bounds -2\";
+}
+"
+        );
+    }
+}
@@ -0,0 +1,541 @@
+//! The handheld game console from day 8, a tiny virtual machine with a single accumulator
+//! register and an instruction pointer.
+//!
+//! The console is generic over its instruction set. `Ins` is the instruction set from the puzzle,
+//! a different instruction set can be run by implementing `Instruction` for it.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+/// the registers of the console
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Registers {
+    /// the accumulator
+    pub acc: i64,
+    /// the instruction pointer, the index of the next instruction to execute. A jump can move
+    /// it outside of the program, including to a negative index
+    pub ip: isize,
+}
+
+/// an instruction that can be run by the `Console`
+pub trait Instruction {
+    /// executes this instruction, updating the registers. The instruction is responsible for
+    /// moving the instruction pointer
+    fn execute(&self, regs: &mut Registers);
+}
+
+/// All the possible Instructions of the puzzle
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Ins {
+    ACC(i32),
+    JMP(i32),
+    NOP(i32),
+}
+
+impl Ins {
+    /// swaps a NOP to a JMP and a JMP to a NOP. ACC returns itself
+    pub fn swap(ins: &Ins) -> Self {
+        match ins {
+            Ins::NOP(amt) => Ins::JMP(*amt),
+            Ins::JMP(amt) => Ins::NOP(*amt),
+            Ins::ACC(_amt) => *ins,
+        }
+    }
+
+    /// returns true if `ins` is an ACC instruction
+    pub fn is_acc(ins: &Ins) -> bool {
+        matches!(ins, Ins::ACC(_))
+    }
+}
+
+impl Instruction for Ins {
+    fn execute(&self, regs: &mut Registers) {
+        match self {
+            Ins::ACC(amt) => {
+                regs.acc += i64::from(*amt);
+                regs.ip += 1;
+            }
+            Ins::JMP(amt) => {
+                regs.ip += *amt as isize;
+            }
+            Ins::NOP(_amt) => {
+                regs.ip += 1;
+            }
+        }
+    }
+}
+
+/// the reasons a single instruction can fail to parse
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// the line is not of the form `<opcode> <+|-><digits>`
+    Malformed(String),
+    /// the opcode is not one of acc, jmp or nop
+    UnknownOpcode(String),
+    /// the operand does not fit in an i32
+    BadOperand(String),
+}
+
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseErrorKind::Malformed(s) => {
+                write!(
+                    f,
+                    "expected an instruction like \"acc +1\" but found {:?}",
+                    s
+                )
+            }
+            ParseErrorKind::UnknownOpcode(s) => write!(f, "unknown opcode {:?}", s),
+            ParseErrorKind::BadOperand(s) => write!(f, "operand {:?} is out of range", s),
+        }
+    }
+}
+
+/// an error parsing a program, along with the (1 based) line number it occurred on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub kind: ParseErrorKind,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.kind)
+    }
+}
+
+impl Error for ParseError {}
+
+impl FromStr for Ins {
+    type Err = ParseErrorKind;
+
+    /// parses a single instruction. The whole string must be the instruction,
+    /// only surrounding whitespace is ignored
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        lazy_static! {
+            static ref RE: Regex =
+                Regex::new(r"^(?P<ins>\w+) (?P<amount>[+-]\d+)$").expect("valid RegEx");
+        }
+        let caps = RE
+            .captures(s.trim())
+            .ok_or_else(|| ParseErrorKind::Malformed(s.to_string()))?;
+        let amount = caps["amount"]
+            .parse::<i32>()
+            .map_err(|_| ParseErrorKind::BadOperand(caps["amount"].to_string()))?;
+        match &caps["ins"] {
+            "acc" => Ok(Ins::ACC(amount)),
+            "jmp" => Ok(Ins::JMP(amount)),
+            "nop" => Ok(Ins::NOP(amount)),
+            other => Err(ParseErrorKind::UnknownOpcode(other.to_string())),
+        }
+    }
+}
+
+impl Display for Ins {
+    /// writes the instruction in its canonical form, such as `acc +1` or `jmp -3`
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Ins::ACC(amt) => write!(f, "acc {:+}", amt),
+            Ins::JMP(amt) => write!(f, "jmp {:+}", amt),
+            Ins::NOP(amt) => write!(f, "nop {:+}", amt),
+        }
+    }
+}
+
+/// parse lines of a program into a Vector of `Ins`tructions. Blank lines are skipped, but still
+/// count towards the line numbers reported in a `ParseError`
+pub fn parse_program<I, S>(lines: I) -> Result<Vec<Ins>, ParseError>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    lines
+        .into_iter()
+        .enumerate()
+        .filter(|(_, line)| !line.as_ref().trim().is_empty())
+        .map(|(idx, line)| {
+            line.as_ref().parse::<Ins>().map_err(|kind| ParseError {
+                line: idx + 1,
+                kind,
+            })
+        })
+        .collect()
+}
+
+/// parse the program in the file pointed to by `filename`
+pub fn read_program<P>(filename: P) -> Result<Vec<Ins>, Box<dyn Error>>
+where
+    P: AsRef<Path>,
+{
+    let file = File::open(filename)?;
+    let lines = io::BufReader::new(file)
+        .lines()
+        .collect::<io::Result<Vec<String>>>()?;
+    Ok(parse_program(lines)?)
+}
+
+/// returns the program in canonical form, one instruction per line
+pub fn disassemble(program: &[Ins]) -> String {
+    program.iter().map(|ins| format!("{}\n", ins)).collect()
+}
+
+/// the result of running a program until it either halts or loops
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// the program stopped by moving to the instruction immediately after its last instruction,
+    /// holds the final accumulator value
+    Halted(i64),
+    /// the program was about to execute an instruction a second time. Holds the accumulator
+    /// value and the instruction pointer of the instruction that would have been repeated
+    Looped(i64, usize),
+    /// the instruction at index `from` jumped past the end of the program, to index `to`
+    JumpedPastEnd { acc: i64, from: usize, to: isize },
+    /// the instruction at index `from` jumped before the start of the program, to index `to`
+    JumpedBeforeStart { acc: i64, from: usize, to: isize },
+}
+
+/// a handheld console that is loaded with a program
+#[derive(Debug, Clone)]
+pub struct Console<I = Ins> {
+    program: Vec<I>,
+    regs: Registers,
+    // visited[i] is true if instruction i has been executed
+    visited: Vec<bool>,
+    // the indices of the executed instructions, in execution order
+    history: Vec<usize>,
+}
+
+impl<I: Instruction> Console<I> {
+    pub fn new(program: Vec<I>) -> Self {
+        let visited = vec![false; program.len()];
+        Console {
+            program,
+            regs: Registers::default(),
+            visited,
+            history: vec![],
+        }
+    }
+
+    pub fn program(&self) -> &[I] {
+        &self.program
+    }
+
+    pub fn acc(&self) -> i64 {
+        self.regs.acc
+    }
+
+    pub fn ip(&self) -> isize {
+        self.regs.ip
+    }
+
+    /// returns the index of the next instruction to execute, or `None` if the instruction
+    /// pointer is outside of the program
+    pub fn current(&self) -> Option<usize> {
+        if self.regs.ip >= 0 && (self.regs.ip as usize) < self.program.len() {
+            Some(self.regs.ip as usize)
+        } else {
+            None
+        }
+    }
+
+    /// returns true if the instruction pointer is outside of the program, either because the
+    /// program halted normally or because it jumped out of bounds
+    pub fn is_halted(&self) -> bool {
+        self.current().is_none()
+    }
+
+    /// returns true if the instruction at the instruction pointer has already been executed
+    pub fn is_looping(&self) -> bool {
+        self.current().is_some_and(|ip| self.visited[ip])
+    }
+
+    /// returns the indices of all instructions that have been executed, in execution order
+    pub fn executed(&self) -> &[usize] {
+        &self.history
+    }
+
+    /// executes the instruction at the instruction pointer. Returns false, without executing
+    /// anything, if the program has halted
+    pub fn step(&mut self) -> bool {
+        match self.current() {
+            Some(ip) => {
+                self.visited[ip] = true;
+                self.history.push(ip);
+                self.program[ip].execute(&mut self.regs);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// returns the outcome of the program if it has stopped running, i.e. it has halted, jumped
+    /// out of bounds, or is about to execute an instruction for the second time.
+    /// Returns `None` while the program can keep running
+    pub fn outcome(&self) -> Option<Outcome> {
+        let acc = self.regs.acc;
+        let to = self.regs.ip;
+        match self.current() {
+            Some(ip) if self.is_looping() => Some(Outcome::Looped(acc, ip)),
+            Some(_) => None,
+            None if to == self.program.len() as isize => Some(Outcome::Halted(acc)),
+            None => {
+                // the last executed instruction jumped out of bounds. An empty program
+                // has no instructions to execute and so always halts
+                let from = *self.history.last().expect("an instruction was executed");
+                if to < 0 {
+                    Some(Outcome::JumpedBeforeStart { acc, from, to })
+                } else {
+                    Some(Outcome::JumpedPastEnd { acc, from, to })
+                }
+            }
+        }
+    }
+
+    /// runs the program until it halts, or until an instruction is about to be executed
+    /// for the second time
+    pub fn run_until_loop_or_halt(&mut self) -> Outcome {
+        loop {
+            if let Some(outcome) = self.outcome() {
+                return outcome;
+            }
+            self.step();
+        }
+    }
+
+    /// clears the registers and execution history, ready to run the program again
+    pub fn reset(&mut self) {
+        self.regs = Registers::default();
+        self.visited.iter_mut().for_each(|v| *v = false);
+        self.history.clear();
+    }
+}
+
+impl<I: Instruction + Display> Console<I> {
+    /// runs the program like `run_until_loop_or_halt`, writing a trace line to `out` for each
+    /// executed instruction, with the instruction pointer and the accumulator after the
+    /// instruction ran. If the program loops, the instruction that would be repeated is
+    /// written last, marked with `<--`
+    pub fn run_traced<W: Write>(&mut self, out: &mut W) -> io::Result<Outcome> {
+        writeln!(out, "{:>5} | {:<10} | acc", "ip", "ins")?;
+        loop {
+            if let Some(outcome) = self.outcome() {
+                if let Outcome::Looped(_, ip) = outcome {
+                    let ins = self.program[ip].to_string();
+                    writeln!(
+                        out,
+                        "{:>5} | {:<10} | <-- would execute a second time",
+                        ip, ins
+                    )?;
+                }
+                return Ok(outcome);
+            }
+            let ip = self.ip();
+            let ins = self.program[ip as usize].to_string();
+            self.step();
+            writeln!(out, "{:>5} | {:<10} | {}", ip, ins, self.regs.acc)?;
+        }
+    }
+}
+
+/// the example program from the puzzle description, shared by the tests of the day 8 modules
+#[cfg(test)]
+pub(crate) const EXAMPLE: &str = "nop +0
+acc +1
+jmp +4
+acc +3
+jmp -3
+acc -99
+acc +1
+jmp -4
+acc +6";
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        disassemble, parse_program, Console, Ins, Instruction, Outcome, ParseError, ParseErrorKind,
+        Registers, EXAMPLE,
+    };
+
+    #[test]
+    fn parses_signed_amounts() {
+        let program = parse_program(EXAMPLE.lines()).unwrap();
+        assert_eq!(program.len(), 9);
+        assert_eq!(program[0], Ins::NOP(0));
+        assert_eq!(program[4], Ins::JMP(-3));
+        assert_eq!(program[5], Ins::ACC(-99));
+    }
+
+    #[test]
+    fn rejects_unknown_opcodes() {
+        let err = parse_program("nop +0\njnp +3\n".lines()).unwrap_err();
+        assert_eq!(
+            err,
+            ParseError {
+                line: 2,
+                kind: ParseErrorKind::UnknownOpcode("jnp".to_string())
+            }
+        );
+        assert_eq!(err.to_string(), "line 2: unknown opcode \"jnp\"");
+    }
+
+    #[test]
+    fn rejects_malformed_instructions() {
+        for line in &[
+            "acc 1",
+            "acc +1 junk",
+            "x acc +1",
+            "acc +",
+            "acc",
+            "acc +1a",
+        ] {
+            assert_eq!(
+                line.parse::<Ins>(),
+                Err(ParseErrorKind::Malformed(line.to_string())),
+                "{}",
+                line
+            );
+        }
+        assert_eq!(
+            "jmp +99999999999".parse::<Ins>(),
+            Err(ParseErrorKind::BadOperand("+99999999999".to_string()))
+        );
+    }
+
+    #[test]
+    fn blank_lines_are_skipped_but_counted() {
+        let err = parse_program(vec!["acc +1", "", "acc +1 ", "nop"]).unwrap_err();
+        assert_eq!(err.line, 4);
+        assert_eq!(parse_program(vec!["acc +1\r", ""]), Ok(vec![Ins::ACC(1)]));
+    }
+
+    #[test]
+    fn disassembles_to_canonical_form() {
+        let program = parse_program(EXAMPLE.lines()).unwrap();
+        assert_eq!(disassemble(&program), format!("{}\n", EXAMPLE));
+        assert_eq!(Ins::NOP(-0).to_string(), "nop +0");
+    }
+
+    #[test]
+    fn step_executes_one_instruction() {
+        let mut console = Console::new(parse_program(EXAMPLE.lines()).unwrap());
+        assert!(console.step());
+        assert!(console.step());
+        assert_eq!((console.acc(), console.ip()), (1, 2));
+        assert!(console.step());
+        assert!(console.step());
+        assert_eq!(console.ip(), 7);
+        assert!(console.step());
+        assert!(console.step());
+        assert_eq!(console.executed(), &[0, 1, 2, 6, 7, 3]);
+    }
+
+    #[test]
+    fn accumulator_holds_sums_beyond_i32() {
+        let program = parse_program(vec!["acc +2147483647", "acc +1"]).unwrap();
+        let mut console = Console::new(program);
+        assert_eq!(
+            console.run_until_loop_or_halt(),
+            Outcome::Halted(2_147_483_648)
+        );
+    }
+
+    #[test]
+    fn example_loops() {
+        let mut console = Console::new(parse_program(EXAMPLE.lines()).unwrap());
+        assert_eq!(console.run_until_loop_or_halt(), Outcome::Looped(5, 1));
+        console.reset();
+        assert_eq!(console.run_until_loop_or_halt(), Outcome::Looped(5, 1));
+    }
+
+    #[test]
+    fn repaired_example_halts() {
+        let mut program = parse_program(EXAMPLE.lines()).unwrap();
+        program[7] = Ins::swap(&program[7]);
+        let mut console = Console::new(program);
+        assert_eq!(console.run_until_loop_or_halt(), Outcome::Halted(8));
+        assert!(!console.step());
+    }
+
+    #[test]
+    fn jumping_past_the_end_is_not_halting() {
+        let mut console = Console::new(vec![Ins::ACC(2), Ins::JMP(5), Ins::NOP(0)]);
+        assert_eq!(
+            console.run_until_loop_or_halt(),
+            Outcome::JumpedPastEnd {
+                acc: 2,
+                from: 1,
+                to: 6
+            }
+        );
+        assert!(console.is_halted());
+        assert!(!console.step());
+    }
+
+    #[test]
+    fn jumping_before_the_start_is_not_halting() {
+        let mut console = Console::new(vec![Ins::NOP(0), Ins::ACC(1), Ins::JMP(-3)]);
+        assert_eq!(
+            console.run_until_loop_or_halt(),
+            Outcome::JumpedBeforeStart {
+                acc: 1,
+                from: 2,
+                to: -1
+            }
+        );
+    }
+
+    #[test]
+    fn empty_program_halts() {
+        let mut console = Console::new(Vec::<Ins>::new());
+        assert_eq!(console.run_until_loop_or_halt(), Outcome::Halted(0));
+    }
+
+    #[test]
+    fn traces_each_executed_instruction() {
+        let mut console = Console::new(vec![Ins::ACC(2), Ins::NOP(0), Ins::JMP(-2)]);
+        let mut out = vec![];
+        let outcome = console.run_traced(&mut out).unwrap();
+        assert_eq!(outcome, Outcome::Looped(2, 0));
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "   ip | ins        | acc
+    0 | acc +2     | 2
+    1 | nop +0     | 2
+    2 | jmp -2     | 2
+    0 | acc +2     | <-- would execute a second time
+"
+        );
+    }
+
+    /// a different instruction set, to check that the console runs any `Instruction`
+    enum Doubler {
+        Inc,
+        Double,
+    }
+
+    impl Instruction for Doubler {
+        fn execute(&self, regs: &mut Registers) {
+            match self {
+                Doubler::Inc => regs.acc += 1,
+                Doubler::Double => regs.acc *= 2,
+            }
+            regs.ip += 1;
+        }
+    }
+
+    #[test]
+    fn runs_other_instruction_sets() {
+        let mut console = Console::new(vec![Doubler::Inc, Doubler::Double, Doubler::Double]);
+        assert_eq!(console.run_until_loop_or_halt(), Outcome::Halted(4));
+    }
+}
@@ -0,0 +1,285 @@
+//! An interactive step debugger for the handheld `Console`.
+//!
+//! The debugger reads one command per line, so it can be driven from stdin or from a script:
+//!
+//! ```text
+//! step [n]       s   execute the next n instructions (default 1)
+//! continue       c   run until a breakpoint is hit or the program stops
+//! break <addr>   b   set a breakpoint at an instruction address
+//! delete <addr>  d   remove a breakpoint
+//! print          p   show the accumulator and instruction pointer
+//! list           l   show the instructions around the instruction pointer
+//! reset          r   restart the program from the beginning
+//! help           h   show the commands
+//! quit           q   exit the debugger
+//! ```
+
+use crate::console::{Console, Instruction, Outcome};
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::io;
+use std::io::{BufRead, Write};
+
+const HELP: &str = "commands:
+  step [n]       s   execute the next n instructions (default 1)
+  continue       c   run until a breakpoint is hit or the program stops
+  break <addr>   b   set a breakpoint at an instruction address
+  delete <addr>  d   remove a breakpoint
+  print          p   show the accumulator and instruction pointer
+  list           l   show the instructions around the instruction pointer
+  reset          r   restart the program from the beginning
+  help           h   show the commands
+  quit           q   exit the debugger";
+
+/// the number of instructions shown before and after the instruction pointer by `list`
+const LIST_CONTEXT: usize = 3;
+
+/// a step debugger wrapping a `Console`
+#[derive(Debug)]
+pub struct Debugger<I> {
+    console: Console<I>,
+    breakpoints: BTreeSet<usize>,
+}
+
+impl<I: Instruction + Display> Debugger<I> {
+    pub fn new(console: Console<I>) -> Self {
+        Debugger {
+            console,
+            breakpoints: BTreeSet::new(),
+        }
+    }
+
+    pub fn console(&self) -> &Console<I> {
+        &self.console
+    }
+
+    /// reads commands from `input` until `quit` or the end of the input, writing all
+    /// output to `out`
+    pub fn run<R: BufRead, W: Write>(&mut self, input: R, out: &mut W) -> io::Result<()> {
+        writeln!(out, "type help for a list of commands")?;
+        write!(out, "(dbg) ")?;
+        out.flush()?;
+        for line in input.lines() {
+            if !self.execute(&line?, out)? {
+                break;
+            }
+            write!(out, "(dbg) ")?;
+            out.flush()?;
+        }
+        writeln!(out)
+    }
+
+    /// executes a single debugger command. Returns false if the debugger should quit
+    pub fn execute<W: Write>(&mut self, command: &str, out: &mut W) -> io::Result<bool> {
+        let words: Vec<&str> = command.split_whitespace().collect();
+        match words.as_slice() {
+            [] => {}
+            ["s"] | ["step"] => self.step(1, out)?,
+            ["s", n] | ["step", n] => match n.parse() {
+                Ok(n) => self.step(n, out)?,
+                Err(_) => writeln!(out, "invalid step count {}", n)?,
+            },
+            ["c"] | ["continue"] => self.resume(out)?,
+            ["b", addr] | ["break", addr] => match self.address(addr) {
+                Some(addr) => {
+                    self.breakpoints.insert(addr);
+                    writeln!(out, "breakpoint set at {}", addr)?;
+                }
+                None => writeln!(out, "invalid address {}", addr)?,
+            },
+            ["d", addr] | ["delete", addr] => match self.address(addr) {
+                Some(addr) if self.breakpoints.remove(&addr) => {
+                    writeln!(out, "breakpoint removed from {}", addr)?
+                }
+                _ => writeln!(out, "no breakpoint at {}", addr)?,
+            },
+            ["p"] | ["print"] => writeln!(
+                out,
+                "acc = {}, ip = {}",
+                self.console.acc(),
+                self.console.ip()
+            )?,
+            ["l"] | ["list"] => self.list(out)?,
+            ["r"] | ["reset"] => {
+                self.console.reset();
+                writeln!(out, "program reset")?;
+            }
+            ["h"] | ["help"] => writeln!(out, "{}", HELP)?,
+            ["q"] | ["quit"] => return Ok(false),
+            _ => writeln!(out, "unknown command {:?}, type help for a list", command)?,
+        }
+        Ok(true)
+    }
+
+    /// parses `addr` as an instruction address within the program
+    fn address(&self, addr: &str) -> Option<usize> {
+        addr.parse()
+            .ok()
+            .filter(|&addr| addr < self.console.program().len())
+    }
+
+    /// executes up to `n` instructions, stopping early if the program stops
+    fn step<W: Write>(&mut self, n: usize, out: &mut W) -> io::Result<()> {
+        for _ in 0..n {
+            if let Some(outcome) = self.console.outcome() {
+                return self.report(outcome, out);
+            }
+            self.step_one(out)?;
+        }
+        if let Some(outcome) = self.console.outcome() {
+            self.report(outcome, out)?;
+        }
+        Ok(())
+    }
+
+    /// runs until a breakpoint is reached or the program stops. A breakpoint at the current
+    /// instruction does not stop the program, so that `continue` always makes progress
+    fn resume<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        let mut first = true;
+        loop {
+            if let Some(outcome) = self.console.outcome() {
+                return self.report(outcome, out);
+            }
+            let ip = self
+                .console
+                .current()
+                .expect("a running program is in bounds");
+            if !first && self.breakpoints.contains(&ip) {
+                return writeln!(out, "breakpoint at {}", ip);
+            }
+            first = false;
+            self.console.step();
+        }
+    }
+
+    /// executes the next instruction and writes it, along with the new accumulator
+    fn step_one<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        let ip = self
+            .console
+            .current()
+            .expect("a running program is in bounds");
+        let ins = self.console.program()[ip].to_string();
+        self.console.step();
+        writeln!(
+            out,
+            "{:>5} | {:<10} | acc = {}",
+            ip,
+            ins,
+            self.console.acc()
+        )
+    }
+
+    /// writes the instructions around the instruction pointer, marking the current instruction
+    /// with `>` and breakpoints with `*`
+    fn list<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let len = self.console.program().len();
+        let ip = self.console.ip().max(0) as usize;
+        let start = ip.saturating_sub(LIST_CONTEXT);
+        let end = (ip + LIST_CONTEXT + 1).min(len);
+        for addr in start..end {
+            let current = if addr == ip { '>' } else { ' ' };
+            let breakpoint = if self.breakpoints.contains(&addr) {
+                '*'
+            } else {
+                ' '
+            };
+            writeln!(
+                out,
+                "{}{} {:>5} | {}",
+                current,
+                breakpoint,
+                addr,
+                self.console.program()[addr]
+            )?;
+        }
+        Ok(())
+    }
+
+    fn report<W: Write>(&self, outcome: Outcome, out: &mut W) -> io::Result<()> {
+        match outcome {
+            Outcome::Halted(acc) => writeln!(out, "program halted with acc = {}", acc),
+            Outcome::Looped(acc, ip) => writeln!(
+                out,
+                "instruction {} ({}) would execute a second time, acc = {}",
+                ip,
+                self.console.program()[ip],
+                acc
+            ),
+            Outcome::JumpedPastEnd { acc, from, to } => writeln!(
+                out,
+                "instruction {} jumped past the end of the program to {}, acc = {}",
+                from, to, acc
+            ),
+            Outcome::JumpedBeforeStart { acc, from, to } => writeln!(
+                out,
+                "instruction {} jumped before the start of the program to {}, acc = {}",
+                from, to, acc
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Debugger;
+    use crate::console::{parse_program, Console, EXAMPLE};
+
+    /// runs the debugger over the `script` of commands and returns its output
+    fn debug(script: &str) -> String {
+        let program = parse_program(EXAMPLE.lines()).unwrap();
+        let mut debugger = Debugger::new(Console::new(program));
+        let mut out = vec![];
+        debugger.run(script.as_bytes(), &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn steps_and_prints_registers() {
+        let out = debug("step 2\nprint\n");
+        assert!(out.contains("    0 | nop +0     | acc = 0\n"));
+        assert!(out.contains("    1 | acc +1     | acc = 1\n"));
+        assert!(out.contains("acc = 1, ip = 2\n"));
+    }
+
+    #[test]
+    fn script_leaves_console_at_last_instruction() {
+        let program = parse_program(EXAMPLE.lines()).unwrap();
+        let mut debugger = Debugger::new(Console::new(program));
+        debugger.run("step 3\n".as_bytes(), &mut vec![]).unwrap();
+        assert_eq!(debugger.console().executed(), &[0, 1, 2]);
+        assert_eq!((debugger.console().acc(), debugger.console().ip()), (1, 6));
+    }
+
+    #[test]
+    fn continue_stops_at_breakpoints_and_loops() {
+        let out = debug("b 4\nc\np\nc\nc\n");
+        assert!(out.contains("breakpoint set at 4\n"));
+        assert!(out.contains("breakpoint at 4\n"));
+        assert!(out.contains("acc = 5, ip = 4\n"));
+        assert!(out.contains("instruction 1 (acc +1) would execute a second time, acc = 5\n"));
+    }
+
+    #[test]
+    fn lists_instructions_around_ip() {
+        let out = debug("b 1\nlist\n");
+        assert!(out.contains(">      0 | nop +0\n"));
+        assert!(out.contains(" *     1 | acc +1\n"));
+        assert!(out.contains("       3 | acc +3\n"));
+        assert!(!out.contains("       4 | jmp -3\n"));
+    }
+
+    #[test]
+    fn quit_stops_reading_commands() {
+        let out = debug("quit\nstep\n");
+        assert!(!out.contains("nop +0"));
+    }
+
+    #[test]
+    fn rejects_bad_commands() {
+        let out = debug("b 99\nd 2\nstep x\nfoo\n");
+        assert!(out.contains("invalid address 99\n"));
+        assert!(out.contains("no breakpoint at 2\n"));
+        assert!(out.contains("invalid step count x\n"));
+        assert!(out.contains("unknown command \"foo\""));
+    }
+}
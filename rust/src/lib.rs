@@ -0,0 +1,6 @@
+//! Helpers shared between the Advent of Code solutions in `src/bin`
+
+pub mod cfg;
+pub mod console;
+pub mod debugger;
+pub mod records;
@@ -0,0 +1,102 @@
+//! Splits puzzle input into records that are separated by one or more blank lines.
+//!
+//! Several puzzles (days 4 and 6) spread a single record across multiple lines and use a blank
+//! line to mark the end of a record. `Records` handles the awkward edge cases in one place:
+//! leading/trailing blank lines, runs of blank lines, whitespace-only lines and `\r\n` line endings.
+
+use std::fs::File;
+use std::io;
+use std::io::BufRead;
+use std::path::Path;
+
+/// An iterator that groups lines into blank-line separated records.
+/// Each record is returned as a `Vec` of its (non-blank) lines, with trailing whitespace removed.
+/// Empty records are never returned.
+#[derive(Debug)]
+pub struct Records<I> {
+    lines: I,
+}
+
+impl<I, S> Iterator for Records<I>
+where
+    I: Iterator<Item = S>,
+    S: AsRef<str>,
+{
+    type Item = Vec<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut record = vec![];
+        for line in self.lines.by_ref() {
+            // trim_end also strips the '\r' left over from a "\r\n" line ending
+            let line = line.as_ref().trim_end();
+            if !line.trim_start().is_empty() {
+                record.push(line.to_string());
+            } else if !record.is_empty() {
+                return Some(record);
+            }
+        }
+        if record.is_empty() {
+            None
+        } else {
+            Some(record)
+        }
+    }
+}
+
+/// returns a `Records` iterator over the given `lines`
+pub fn records<I>(lines: I) -> Records<I::IntoIter>
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    Records {
+        lines: lines.into_iter(),
+    }
+}
+
+/// reads the file pointed to by `filename` and splits it into blank-line separated records
+pub fn read_records<P>(filename: P) -> io::Result<Vec<Vec<String>>>
+where
+    P: AsRef<Path>,
+{
+    let file = File::open(filename)?;
+    let lines = io::BufReader::new(file)
+        .lines()
+        .collect::<io::Result<Vec<String>>>()?;
+    Ok(records(lines).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::records;
+
+    #[test]
+    fn splits_on_single_blank_line() {
+        let recs: Vec<Vec<String>> = records("abc\n\na\nb\nc".lines()).collect();
+        assert_eq!(recs, vec![vec!["abc"], vec!["a", "b", "c"]]);
+    }
+
+    #[test]
+    fn last_record_without_trailing_blank_line_is_returned() {
+        let recs: Vec<Vec<String>> = records(vec!["a", "b"]).collect();
+        assert_eq!(recs, vec![vec!["a", "b"]]);
+    }
+
+    #[test]
+    fn ignores_leading_trailing_and_repeated_blank_lines() {
+        let recs: Vec<Vec<String>> = records("\n\na\n\n\n  \nb\n\n\n".split('\n')).collect();
+        assert_eq!(recs, vec![vec!["a"], vec!["b"]]);
+    }
+
+    #[test]
+    fn handles_crlf_line_endings() {
+        let recs: Vec<Vec<String>> = records("a\r\nb\r\n\r\nc\r\n".split('\n')).collect();
+        assert_eq!(recs, vec![vec!["a", "b"], vec!["c"]]);
+    }
+
+    #[test]
+    fn empty_input_has_no_records() {
+        assert_eq!(records(Vec::<String>::new()).count(), 0);
+        assert_eq!(records(vec!["", " ", ""]).count(), 0);
+    }
+}